@@ -0,0 +1,421 @@
+use crate::lexer::{self, Lexeme, Token};
+use crate::parser::{ParseError, ParseErrorKind};
+use crate::Error;
+
+const WIDTH: usize = 80;
+const INDENT: usize = 4;
+
+// Forms which keep their first parameters on the line of the name, like `(def (f x)` or `(if condition`.
+// Other lists keep only the first item there.
+const HEADS: &[(&str, usize)] = &[
+    ("def", 1),
+    ("defmacro", 1),
+    ("defsyntax", 1),
+    ("lambda", 1),
+    ("let", 1),
+    ("set", 1),
+    ("if", 1),
+    ("struct", 1),
+    ("import", 1),
+    ("catch", 1),
+    ("::", 2),
+];
+
+enum Kind {
+    // numbers, strings and symbols are printed as they were written
+    Atom(String),
+    Comment(String),
+    // quotes and `#;` before a form
+    Prefixed(&'static str, Box<Node>),
+    List(&'static str, Vec<Node>, &'static str),
+}
+
+struct Node {
+    kind: Kind,
+    // the node begins on the line where the previous lexeme ends
+    same_line: bool,
+    // there is an empty line before the node
+    blank_before: bool,
+}
+
+impl Node {
+    fn is_comment(&self) -> bool {
+        matches!(self.kind, Kind::Comment(_))
+    }
+
+    // the node on one line, lists with comments and multi-line strings can't be flat,
+    // a `do` of several forms is never flat too
+    fn flat(&self) -> Option<String> {
+        match &self.kind {
+            Kind::List(_, children, _) if self.is_do() && children.len() > 2 => None,
+            Kind::Atom(text) if !text.contains('\n') => Some(text.clone()),
+            Kind::Atom(_) | Kind::Comment(_) => None,
+            Kind::Prefixed(prefix, node) => Some(format!("{}{}", prefix, node.flat()?)),
+            Kind::List(open, children, close) => {
+                let children = children
+                    .iter()
+                    .map(Node::flat)
+                    .collect::<Option<Vec<_>>>()?;
+                Some(format!("{}{}{}", open, children.join(" "), close))
+            }
+        }
+    }
+
+    fn head_count(&self) -> usize {
+        match &self.kind {
+            Kind::List(_, children, _) => match children.first().map(|child| &child.kind) {
+                Some(Kind::Atom(name)) => HEADS
+                    .iter()
+                    .find(|(head, _)| head == name)
+                    .map_or(0, |(_, count)| *count),
+                _ => 0,
+            },
+            _ => 0,
+        }
+    }
+
+    fn is_do(&self) -> bool {
+        match &self.kind {
+            Kind::List("(", children, _) => {
+                matches!(children.first().map(|child| &child.kind), Some(Kind::Atom(name)) if name == "do")
+            }
+            _ => false,
+        }
+    }
+}
+
+// Builds the tree of the lexemes, the text of every lexeme is taken from the source
+struct Builder {
+    lines: Vec<Vec<char>>,
+    lexemes: Vec<Lexeme>,
+    index: usize,
+    previous_end: u32,
+}
+
+impl Builder {
+    fn text(&self, lexeme: &Lexeme) -> String {
+        let mut text = String::new();
+        for line in lexeme.line..=lexeme.end_line {
+            let chars = &self.lines[line as usize - 1];
+            let first = if line == lexeme.line {
+                lexeme.line_char as usize - 1
+            } else {
+                0
+            };
+            let last = if line == lexeme.end_line {
+                (lexeme.end_line_char as usize).min(chars.len())
+            } else {
+                chars.len()
+            };
+            if line != lexeme.line {
+                text.push('\n');
+            }
+            text.extend(&chars[first.min(last)..last]);
+        }
+        text
+    }
+
+    fn error<T>(&self, lexeme: Option<&Lexeme>) -> Result<T, Error> {
+        let error = match lexeme {
+            Some(lexeme) => ParseError {
+                kind: ParseErrorKind::UnexpectedToken(lexeme.token.clone()),
+                start: (lexeme.line, lexeme.line_char),
+                end: (lexeme.end_line, lexeme.end_line_char),
+            },
+            None => {
+                let last = self.lexemes.last();
+                let end = last.map_or((1, 0), |last| (last.end_line, last.end_line_char));
+                ParseError {
+                    kind: ParseErrorKind::UnexpectedEndOfFile,
+                    start: (end.0, end.1 + 1),
+                    end: (end.0, end.1 + 1),
+                }
+            }
+        };
+        Err(Error::Parse(vec![error]))
+    }
+
+    fn next(&mut self) -> Option<Lexeme> {
+        let lexeme = self.lexemes.get(self.index).cloned();
+        self.index += 1;
+        lexeme
+    }
+
+    // nodes till the close bracket, or till the end of file when there is no bracket
+    fn nodes(&mut self, close: Option<&'static str>) -> Result<Vec<Node>, Error> {
+        let mut nodes = vec![];
+        loop {
+            let lexeme = self.lexemes.get(self.index);
+            let closing = match lexeme.map(|lexeme| &lexeme.token) {
+                Some(Token::CloseBracket) => Some(")"),
+                Some(Token::CloseBrace) => Some("}"),
+                Some(Token::CloseSquareBracket) => Some("]"),
+                Some(_) => None,
+                None if close.is_none() => return Ok(nodes),
+                None => return self.error(None),
+            };
+            match closing {
+                Some(closing) if Some(closing) == close => {
+                    let lexeme = self.next().unwrap();
+                    self.previous_end = lexeme.end_line;
+                    return Ok(nodes);
+                }
+                Some(_) => return self.error(lexeme),
+                None => nodes.push(self.node()?),
+            }
+        }
+    }
+
+    fn node(&mut self) -> Result<Node, Error> {
+        let lexeme = match self.next() {
+            Some(lexeme) => lexeme,
+            None => return self.error(None),
+        };
+        let same_line = lexeme.line == self.previous_end;
+        let blank_before = lexeme.line > self.previous_end + 1;
+        self.previous_end = lexeme.end_line;
+
+        let kind = match &lexeme.token {
+            Token::OpenBracket => Kind::List("(", self.nodes(Some(")"))?, ")"),
+            Token::OpenBrace => Kind::List("{", self.nodes(Some("}"))?, "}"),
+            Token::OpenSquareBracket => Kind::List("[", self.nodes(Some("]"))?, "]"),
+            Token::Quote => Kind::Prefixed("'", Box::new(self.node()?)),
+            Token::Quasiquote => Kind::Prefixed("`", Box::new(self.node()?)),
+            Token::Unquote => Kind::Prefixed(",", Box::new(self.node()?)),
+            Token::UnquoteSplicing => Kind::Prefixed(",@", Box::new(self.node()?)),
+            Token::DatumComment => Kind::Prefixed("#;", Box::new(self.node()?)),
+            Token::Comment => Kind::Comment(self.text(&lexeme).trim_end().to_string()),
+            Token::CloseBracket | Token::CloseBrace | Token::CloseSquareBracket => {
+                return self.error(Some(&lexeme))
+            }
+            _ => Kind::Atom(self.text(&lexeme)),
+        };
+        Ok(Node {
+            kind,
+            same_line,
+            blank_before,
+        })
+    }
+}
+
+struct Printer {
+    out: String,
+    column: usize,
+}
+
+impl Printer {
+    fn write(&mut self, text: &str) {
+        match text.rfind('\n') {
+            Some(index) => self.column = text[index + 1..].chars().count(),
+            None => self.column += text.chars().count(),
+        }
+        self.out.push_str(text);
+    }
+
+    fn newline(&mut self, indent: usize) {
+        self.out.push('\n');
+        self.out.push_str(&" ".repeat(indent));
+        self.column = indent;
+    }
+
+    fn fits(&self, text: &str) -> bool {
+        self.column + text.chars().count() <= WIDTH
+    }
+
+    // `indent` is the indent of the line where the node begins
+    fn print(&mut self, node: &Node, indent: usize) {
+        if let Some(text) = node.flat().filter(|text| self.fits(text)) {
+            return self.write(&text);
+        }
+        match &node.kind {
+            Kind::Atom(text) | Kind::Comment(text) => self.write(text),
+            Kind::Prefixed(prefix, node) => {
+                self.write(prefix);
+                self.print(node, indent);
+            }
+            Kind::List(open, children, close) => {
+                self.write(open);
+                self.print_items(node, children, indent);
+                self.write(close);
+            }
+        }
+    }
+
+    // The first item and the parameters of the special form stay on the line of the open bracket,
+    // others go on their own lines, the close bracket goes on its own line too.
+    // The last `(do` hangs on the first line like `(def (f x) (do`.
+    // Items of vectors and maps begin on the next line, a value of a map stays with its key.
+    fn print_items(&mut self, list: &Node, children: &[Node], indent: usize) {
+        let mut rest = children;
+        let is_call = matches!(list.kind, Kind::List("(", _, _));
+        if let Some((first, others)) = rest
+            .split_first()
+            .filter(|(first, _)| is_call && !first.is_comment())
+        {
+            self.print(first, indent);
+            rest = others;
+            for _ in 0..list.head_count() {
+                match rest.split_first() {
+                    Some((child, others)) if !child.is_comment() => {
+                        match child.flat().filter(|text| self.fits(&format!(" {}", text))) {
+                            Some(text) => {
+                                self.write(" ");
+                                self.write(&text);
+                                rest = others;
+                            }
+                            None => break,
+                        }
+                    }
+                    _ => break,
+                }
+            }
+        }
+
+        if let [last] = rest {
+            if last.is_do() && self.fits(" (do") {
+                if let Kind::List(open, children, close) = &last.kind {
+                    self.write(" ");
+                    self.write(open);
+                    self.print_items(last, children, indent);
+                    self.write(close);
+                    return;
+                }
+            }
+        }
+
+        let is_map = matches!(list.kind, Kind::List("{", _, _));
+        let mut items = 0;
+        let mut after_key = false;
+        for child in rest {
+            if child.is_comment() && child.same_line || after_key && !child.is_comment() {
+                self.write(" ");
+            } else {
+                if child.blank_before {
+                    self.out.push('\n');
+                }
+                self.newline(indent + INDENT);
+            }
+            self.print(child, indent + INDENT);
+            // a value after a comment goes on its own line, else it would be commented out
+            if !child.is_comment() {
+                items += 1;
+            }
+            after_key = is_map && !child.is_comment() && items % 2 == 1;
+        }
+        self.newline(indent);
+    }
+}
+
+// Prints the code in the house style. The comments and the empty lines between forms are kept,
+// numbers and strings are written as they were.
+pub fn format(text: &str) -> Result<String, Error> {
+    let lexemes = lexer::lex_with_comments(&mut text.chars()).map_err(Error::Lex)?;
+    let mut builder = Builder {
+        lines: text.lines().map(|line| line.chars().collect()).collect(),
+        lexemes,
+        index: 0,
+        previous_end: 0,
+    };
+    let nodes = builder.nodes(None)?;
+
+    let mut printer = Printer {
+        out: String::new(),
+        column: 0,
+    };
+    for (index, node) in nodes.iter().enumerate() {
+        if index > 0 {
+            if node.is_comment() && node.same_line {
+                printer.write(" ");
+            } else {
+                if node.blank_before {
+                    printer.out.push('\n');
+                }
+                printer.newline(0);
+            }
+        }
+        printer.print(node, 0);
+    }
+    if !printer.out.is_empty() {
+        printer.out.push('\n');
+    }
+    Ok(printer.out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    const SAMPLES: &[&str] = &[
+        "(def (f x) (+ x 1))",
+        "# the header\n\n(let   x 5) # five\n(let xs '(1 2 . 3))\n\n\n(println `(a ,x ,@xs))",
+        "(def (fib n) (if (< n 2) n (+ (fib (- n 1)) (fib (- n 2)))))
+(def (long_function_name first_parameter second_parameter) (do (let sum (+ first_parameter second_parameter)) (println \"the sum is\" sum) sum))",
+        "{name \"Bob\" # the name\n age 30 tags [1 2 3]\n # the end\n}",
+        "(let matrix [[1 2] [3 4]]) #| block\n #| nested |# comment |# (println matrix)",
+        "(println 1 #; (ignored form) 2 #;3)\n#;(def (unused) 1)\n(let s \"line one\nline two\")",
+        "(try (throw \"oops\") (catch e (println e)) (finally (println \"done\")))",
+        "(struct Position (x y)) (:: (new Position 1 2) x) (lambda (x . rest) rest) r\"raw \\ string\" 0xff 1/3 2.5e-3",
+    ];
+
+    // the parsed code, positions are not compared
+    fn code(text: &str) -> Vec<String> {
+        let lexemes = lexer::lex(&mut text.chars()).unwrap();
+        parser::parse(&mut lexemes.into_iter())
+            .unwrap()
+            .iter()
+            .map(|value| format!("{:#}", value.content))
+            .collect()
+    }
+
+    #[test]
+    fn formatting_is_stable() {
+        for text in SAMPLES {
+            let formatted = format(text).unwrap();
+            assert_eq!(format(&formatted).unwrap(), formatted, "{}", text);
+        }
+    }
+
+    #[test]
+    fn code_is_kept() {
+        for text in SAMPLES {
+            let formatted = format(text).unwrap();
+            assert_eq!(code(&formatted), code(text), "{}", formatted);
+        }
+    }
+
+    #[test]
+    fn comments_are_kept() {
+        for text in SAMPLES {
+            let formatted = format(text).unwrap();
+            let comments = lexer::lex_with_comments(&mut text.chars())
+                .unwrap()
+                .into_iter()
+                .filter(|lexeme| matches!(lexeme.token, Token::Comment | Token::DatumComment))
+                .count();
+            let kept = lexer::lex_with_comments(&mut formatted.chars())
+                .unwrap()
+                .into_iter()
+                .filter(|lexeme| matches!(lexeme.token, Token::Comment | Token::DatumComment))
+                .count();
+            assert_eq!(kept, comments, "{}", formatted);
+        }
+        let formatted = format(SAMPLES[4]).unwrap();
+        assert!(formatted.contains("#| block\n #| nested |# comment |#"));
+    }
+
+    #[test]
+    fn house_style() {
+        assert_eq!(
+            format("(def (f x)\n(do (let y   (* x 2))\n    (+ y 1)))").unwrap(),
+            "(def (f x) (do\n    (let y (* x 2))\n    (+ y 1)\n))\n"
+        );
+        assert_eq!(
+            format("(println 1)   # one\n\n\n\n(println 2)").unwrap(),
+            "(println 1) # one\n\n(println 2)\n"
+        );
+        assert_eq!(format("[1 2\n 3]").unwrap(), "[1 2 3]\n");
+        assert!(format("(println 1").is_err());
+        assert!(format("(println 1))").is_err());
+    }
+}
@@ -0,0 +1,7 @@
+pub mod format;
+pub mod lexer;
+pub mod parser;
+pub mod runtime;
+pub mod types;
+
+pub use runtime::interpreter::{Error, Interpreter};
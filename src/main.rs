@@ -1,9 +1,22 @@
-use std::{env, fs, io::{stdin, Read}};
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{stdin, Read},
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+};
 
-mod lexer;
-mod parser;
-mod runtime;
-mod types;
+mod repl;
+
+use yal::{
+    format,
+    lexer::{self, LexError},
+    parser::{self, ParseError},
+    runtime::lint::{self, Diagnostic, Severity},
+    types::exception::Exception,
+    Error, Interpreter,
+};
 
 fn lexemes(text: String) {
     match lexer::lex(&mut text.chars()) {
@@ -11,53 +24,297 @@ fn lexemes(text: String) {
             .iter()
             .enumerate()
             .for_each(|(i, l)| println!("{}:\n{:#?}", i, l)),
-        Err(err) => eprintln!("{}", err),
+        Err(err) => print_lex_error(&err, &text),
     }
 }
 
+// the tree of a broken file is partial, the errors are printed after it
 fn tree(text: String) {
     match lexer::lex(&mut text.chars()) {
-        Ok(lexemes) => match parser::parse(&mut lexemes.into_iter()) {
-            Ok(values) => values
+        Ok(lexemes) => {
+            let (values, errors) = parser::parse_partial(&mut lexemes.into_iter());
+            values
                 .iter()
                 .enumerate()
-                .for_each(|(i, v)| println!("{}:\n{:#?}", i, v)),
-            Err(err) => eprintln!("{}", err),
-        },
-        Err(err) => eprintln!("{}", err),
+                .for_each(|(i, v)| println!("{}:\n{:#?}", i, v));
+            print_parse_errors(&errors, &text);
+        }
+        Err(err) => print_lex_error(&err, &text),
     }
 }
 
-fn exec(text: String) {
-    match lexer::lex(&mut text.chars()) {
-        Ok(lexemes) => match parser::parse(&mut lexemes.into_iter()) {
-            Ok(values) => match runtime::execute(&mut values.into_iter()) {
-                Ok(_) => {}
-                Err(err) => {
-                    let lines: Vec<_> = text.lines().collect();
-                    eprintln!("Traceback:");
-                    for position in err.traceback.iter() {
-                        if let Some(pos) = position {
-                            eprintln!("{}-{}", pos.0, pos.1);
-                            eprintln!("{}", lines[pos.0 as usize - 1]);
-                            let mut arrow = std::iter::repeat("-")
-                                .take(pos.1 as usize - 1)
-                                .collect::<String>();
-                            arrow.push('^');
-                            eprintln!("{}", arrow);
-                        }
-                    }
-                    eprintln!("Exception: {:#?}", &err.thrown_object.content);
+// Columns of the char in a terminal. Most of chinese, japanese and korean chars and emoji
+// take two columns, combining marks take none.
+fn char_width(ch: char) -> usize {
+    match ch as u32 {
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x200B..=0x200F => 0,
+        0x20D0..=0x20FF | 0xFE00..=0xFE0F | 0xFE20..=0xFE2F => 0,
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF => 2,
+        0x4E00..=0x9FFF | 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF => 2,
+        0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 => 2,
+        0x1F300..=0x1F64F | 0x1F900..=0x1F9FF | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+// prints the line and marks the chars from start to end,
+// a span over several lines is marked up to the end of the first one
+fn print_source_line(lines: &[String], start: (u32, u32), end: (u32, u32)) {
+    let line = match lines.get(start.0 as usize - 1) {
+        Some(line) => line,
+        None => return,
+    };
+    let last = if end.0 == start.0 {
+        end.1.max(start.1)
+    } else {
+        line.chars().count() as u32
+    };
+    eprintln!("{}", line);
+
+    // tabs are copied, so the marks are under the chars whatever the tab width is
+    let mut arrow = String::new();
+    for (ch, column) in line.chars().zip(1..) {
+        if column >= start.1 && column <= last {
+            arrow.push_str(&"^".repeat(char_width(ch).max(1)));
+        } else if column < start.1 && ch == '\t' {
+            arrow.push('\t');
+        } else if column < start.1 {
+            arrow.push_str(&"-".repeat(char_width(ch)));
+        }
+    }
+    // the end of the line or the file could be marked too
+    if last > line.chars().count() as u32 {
+        let padding = start.1.saturating_sub(line.chars().count() as u32 + 1);
+        arrow.push_str(&"-".repeat(padding as usize));
+        arrow.push('^');
+    }
+    eprintln!("{}", arrow);
+}
+
+fn source_lines(text: &str) -> Vec<String> {
+    text.lines().map(str::to_string).collect()
+}
+
+pub fn print_lex_error(err: &LexError, text: &str) {
+    eprintln!("{}-{}", err.start.0, err.start.1);
+    print_source_line(&source_lines(text), err.start, err.end);
+    eprintln!("Lex error: {}", err);
+}
+
+pub fn print_parse_errors(errors: &[ParseError], text: &str) {
+    let lines = source_lines(text);
+    for err in errors {
+        eprintln!("{}-{}", err.start.0, err.start.1);
+        print_source_line(&lines, err.start, err.end);
+        eprintln!("Parse error: {}", err);
+    }
+}
+
+fn print_exception(exception: &Exception, text: &str) {
+    // sources of the imported modules are read only when they appear in the traceback
+    let mut sources: HashMap<Rc<PathBuf>, Vec<String>> = HashMap::new();
+    let main_lines = source_lines(text);
+
+    let mut current = Some(exception);
+    while let Some(err) = current {
+        eprintln!("Traceback:");
+        for frame in err.traceback.iter() {
+            let pos = match frame.position {
+                Some(pos) => pos,
+                None => continue,
+            };
+            let lines = match &frame.file {
+                Some(file) => {
+                    eprintln!("{}:{}-{}", file.display(), pos.0, pos.1);
+                    sources.entry(file.clone()).or_insert_with(|| {
+                        fs::read_to_string(file.as_path())
+                            .map(|source| source_lines(&source))
+                            .unwrap_or_default()
+                    })
+                }
+                None => {
+                    eprintln!("{}-{}", pos.0, pos.1);
+                    &main_lines
+                }
+            };
+            print_source_line(lines, pos, pos);
+        }
+        eprintln!("Exception: {:#?}", &err.thrown_object.content);
+
+        current = err.previous_exception.as_deref();
+        if current.is_some() {
+            eprintln!();
+            eprintln!("Caused by:");
+        }
+    }
+}
+
+pub fn print_error(err: &Error, text: &str) {
+    match err {
+        Error::Runtime(exception) => print_exception(exception, text),
+        Error::Undefined(errors) => errors
+            .iter()
+            .for_each(|exception| print_exception(exception, text)),
+        Error::Lex(err) => print_lex_error(err, text),
+        Error::Parse(errors) => print_parse_errors(errors, text),
+        Error::Io(message) => eprintln!("{}", message),
+    }
+}
+
+fn bytecode(mut interpreter: Interpreter, text: String) {
+    match interpreter.compile_str(&text) {
+        Ok(prototypes) => prototypes
+            .iter()
+            .enumerate()
+            .for_each(|(i, p)| println!("{}:\n{}", i, p)),
+        Err(err) => print_error(&err, &text),
+    }
+}
+
+fn exec(mut interpreter: Interpreter, text: String) {
+    if let Err(err) = interpreter.eval_str(&text) {
+        print_error(&err, &text);
+    }
+}
+
+// `yal fmt [--check] [files]` rewrites the files in place, stdin goes to stdout when there are no files.
+// With `--check` nothing is written, the unformatted files are listed and the exit code is 1.
+fn fmt(args: &[String]) {
+    let check = args.first().is_some_and(|arg| arg == "--check");
+    let filenames = if check { &args[1..] } else { args };
+
+    if filenames.is_empty() {
+        let mut text = String::new();
+        stdin().read_to_string(&mut text).unwrap();
+        match format::format(&text) {
+            Ok(formatted) if check && formatted != text => {
+                println!("<stdin>");
+                process::exit(1);
+            }
+            Ok(_) if check => {}
+            Ok(formatted) => print!("{}", formatted),
+            Err(err) => {
+                print_error(&err, &text);
+                process::exit(2);
+            }
+        }
+        return;
+    }
+
+    let mut unformatted = false;
+    let mut failed = false;
+    for filename in filenames {
+        let text = match fs::read_to_string(filename) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("Cannot read {}, cause: {}", filename, err);
+                failed = true;
+                continue;
+            }
+        };
+        match format::format(&text) {
+            Ok(formatted) if formatted == text => {}
+            Ok(_) if check => {
+                println!("{}", filename);
+                unformatted = true;
+            }
+            Ok(formatted) => {
+                if let Err(err) = fs::write(filename, formatted) {
+                    eprintln!("Cannot write {}, cause: {}", filename, err);
+                    failed = true;
                 }
-            },
-            Err(err) => eprintln!("{}", err),
-        },
-        Err(err) => eprintln!("{}", err),
+            }
+            Err(err) => {
+                eprintln!("{}:", filename);
+                print_error(&err, &text);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(2);
+    }
+    if unformatted {
+        process::exit(1);
+    }
+}
+
+fn print_diagnostics(diagnostics: &[Diagnostic], filename: Option<&str>, text: &str) {
+    let lines = source_lines(text);
+    for diagnostic in diagnostics {
+        match filename {
+            Some(filename) => {
+                eprintln!("{}:{}-{}", filename, diagnostic.start.0, diagnostic.start.1)
+            }
+            None => eprintln!("{}-{}", diagnostic.start.0, diagnostic.start.1),
+        }
+        print_source_line(&lines, diagnostic.start, diagnostic.end);
+        eprintln!("{}: {}", diagnostic.severity, diagnostic.message);
+    }
+}
+
+// `yal lint [files]` checks the files or stdin, the exit code is 1 if any error is found.
+// Warnings don't change the exit code.
+fn lint(args: &[String]) {
+    let sources = if args.is_empty() {
+        let mut text = String::new();
+        stdin().read_to_string(&mut text).unwrap();
+        vec![(None, Ok(text))]
+    } else {
+        args.iter()
+            .map(|filename| (Some(filename.as_str()), fs::read_to_string(filename)))
+            .collect()
+    };
+
+    let mut failed = false;
+    for (filename, text) in sources {
+        let text = match text {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!(
+                    "Cannot read {}, cause: {}",
+                    filename.unwrap_or_default(),
+                    err
+                );
+                failed = true;
+                continue;
+            }
+        };
+        match lint::lint(&text) {
+            Ok(diagnostics) => {
+                print_diagnostics(&diagnostics, filename, &text);
+                failed |= diagnostics
+                    .iter()
+                    .any(|diagnostic| diagnostic.severity == Severity::Error);
+            }
+            Err(err) => {
+                if let Some(filename) = filename {
+                    eprintln!("{}:", filename);
+                }
+                print_error(&err, &text);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
     }
 }
 
 fn main() {
     let args: Vec<_> = env::args().collect();
+    if args.len() == 1 {
+        repl::run();
+        return;
+    }
+    if args[1] == "fmt" {
+        fmt(&args[2..]);
+        return;
+    }
+    if args[1] == "lint" {
+        lint(&args[2..]);
+        return;
+    }
     if args.len() != 2 {
         eprintln!("There most be 1 argument, given {}", args.len() - 1);
         return;
@@ -77,16 +334,25 @@ fn main() {
             println!("Ctrl^D");
             tree(buffer)
         }
+        "--bytecode" => {
+            let mut buffer = String::new();
+            stdin().read_to_string(&mut buffer).unwrap();
+            println!("Ctrl^D");
+            bytecode(Interpreter::new(), buffer)
+        }
         "--exec" => {
             let mut buffer = String::new();
             stdin().read_to_string(&mut buffer).unwrap();
             println!("Ctrl^D");
-            exec(buffer)
+            exec(Interpreter::new(), buffer)
         }
         filename => {
-            let contents = fs::read_to_string(filename)
-                .expect("Something went wrong reading the file");
-            exec(contents)
-        },
+            let contents =
+                fs::read_to_string(filename).expect("Something went wrong reading the file");
+            match Interpreter::for_file(Path::new(filename)) {
+                Ok(interpreter) => exec(interpreter, contents),
+                Err(err) => eprintln!("{}", err),
+            }
+        }
     }
 }
@@ -1,25 +1,57 @@
+use std::{collections::BTreeMap, fmt::Display, rc::Rc};
+
 use crate::lexer::{Lexeme, Token};
 use crate::types::dot_pair::DotPair;
+use crate::types::map::{Map, MapKey};
 use crate::types::value::Value;
 use crate::types::DynType;
 
-fn is_next(lexeme: Option<Lexeme>) -> Result<Lexeme, String> {
-    match lexeme {
-        Some(v) => Ok(v),
-        None => Err(format!("Unexpected end of file")),
+#[derive(Debug, Clone)]
+pub enum ParseErrorKind {
+    UnexpectedToken(Token),
+    UnexpectedEndOfFile,
+    InvalidMapKey,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub start: (u32, u32),
+    pub end: (u32, u32),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ParseErrorKind::UnexpectedToken(token) => write!(
+                f,
+                "Unexpected token {:?} at {}-{}",
+                token, self.start.0, self.start.1
+            ),
+            ParseErrorKind::UnexpectedEndOfFile => write!(f, "Unexpected end of file"),
+            ParseErrorKind::InvalidMapKey => write!(
+                f,
+                "Map key at {}-{} must be a number, a string or a symbol",
+                self.start.0, self.start.1
+            ),
+        }
     }
 }
 
-fn unexpected_token<T>(lexeme: &Lexeme) -> Result<T, String> {
-    Err(format!(
-        "Unexpected token {:?} at {}-{}",
-        lexeme.token, lexeme.line, lexeme.line_char,
-    ))
+fn unexpected_token<T>(lexeme: &Lexeme) -> Result<T, ParseError> {
+    Err(ParseError {
+        kind: ParseErrorKind::UnexpectedToken(lexeme.token.clone()),
+        start: (lexeme.line, lexeme.line_char),
+        end: (lexeme.end_line, lexeme.end_line_char),
+    })
 }
 
 struct Parser<'a> {
     lexemes: &'a mut dyn Iterator<Item = Lexeme>,
     current_lexeme: Option<Lexeme>,
+    last_end: (u32, u32),
+    // count of the open brackets among the read lexemes
+    depth: i32,
 }
 
 impl<'a> Parser<'a> {
@@ -27,31 +59,76 @@ impl<'a> Parser<'a> {
         Parser::<'a> {
             lexemes,
             current_lexeme: None,
+            last_end: (1, 0),
+            depth: 0,
         }
     }
 
     fn next(&mut self) -> Option<Lexeme> {
+        if let Some(lexeme) = &self.current_lexeme {
+            self.last_end = (lexeme.end_line, lexeme.end_line_char);
+        }
         self.current_lexeme = self.lexemes.next();
+        match self.current_lexeme.as_ref().map(|lexeme| &lexeme.token) {
+            Some(Token::OpenBracket | Token::OpenBrace | Token::OpenSquareBracket) => {
+                self.depth += 1
+            }
+            Some(Token::CloseBracket | Token::CloseBrace | Token::CloseSquareBracket) => {
+                self.depth -= 1
+            }
+            _ => {}
+        }
         self.current_lexeme.clone()
     }
 
-    fn parse_list(&mut self) -> Result<DynType, String> {
-        let Lexeme {
-            line, line_char, ..
-        } = self.current_lexeme.clone().unwrap();
-        let position = Some((line, line_char));
+    // The forms after `#;` are parsed and dropped, the current lexeme becomes the one after them.
+    // `#; #; a b` drops both forms.
+    fn skip_datum_comments(&mut self) -> Result<(), ParseError> {
+        while let Some(Lexeme {
+            token: Token::DatumComment,
+            ..
+        }) = self.current_lexeme
+        {
+            self.next();
+            self.parse_value()?;
+            self.next();
+        }
+        Ok(())
+    }
+
+    fn next_lexeme(&mut self) -> Result<Option<Lexeme>, ParseError> {
+        self.next();
+        self.skip_datum_comments()?;
+        Ok(self.current_lexeme.clone())
+    }
+
+    // the end of file is right after the last lexeme
+    fn is_next(&self, lexeme: Option<Lexeme>) -> Result<Lexeme, ParseError> {
+        let (line, line_char) = self.last_end;
+        lexeme.ok_or(ParseError {
+            kind: ParseErrorKind::UnexpectedEndOfFile,
+            start: (line, line_char + 1),
+            end: (line, line_char + 1),
+        })
+    }
 
-        let left = match is_next(self.current_lexeme.clone())?.token {
+    fn parse_list(&mut self) -> Result<DynType, ParseError> {
+        let current = self.is_next(self.current_lexeme.clone())?;
+        let position = Some((current.line, current.line_char));
+
+        let left = match current.token {
             Token::CloseBracket => return Ok(DynType::Nil),
             _ => self.parse_value()?,
         };
 
-        let current_lexeme = is_next(self.next())?;
+        let current_lexeme = self.next_lexeme()?;
+        let current_lexeme = self.is_next(current_lexeme)?;
         let right = match current_lexeme.token {
             Token::Dot => {
-                self.next();
+                self.next_lexeme()?;
                 let result = self.parse_value()?;
-                let lexeme = is_next(self.next())?;
+                let lexeme = self.next_lexeme()?;
+                let lexeme = self.is_next(lexeme)?;
                 match &lexeme.token {
                     Token::CloseBracket => {}
                     _ => unexpected_token(&lexeme)?,
@@ -64,11 +141,51 @@ impl<'a> Parser<'a> {
         Ok(DynType::Pair(DotPair { left, right }))
     }
 
-    fn parse_value(&mut self) -> Result<Value, String> {
-        let current = match self.current_lexeme.clone() {
-            Some(lexeme) => lexeme,
-            None => return Err(format!("Unexpected end of file during value parsing")),
-        };
+    // items of a vector literal are calculated when the vector is
+    fn parse_vector(&mut self) -> Result<DynType, ParseError> {
+        let mut items = vec![];
+        loop {
+            let lexeme = self.is_next(self.current_lexeme.clone())?;
+            if let Token::CloseSquareBracket = lexeme.token {
+                return Ok(DynType::Vector(Rc::new(items)));
+            }
+            items.push(self.parse_value()?);
+            self.next_lexeme()?;
+        }
+    }
+
+    // Keys of a map literal are taken as written, values are calculated when the map is.
+    // The last value of a repeated key wins.
+    fn parse_map(&mut self) -> Result<DynType, ParseError> {
+        let mut entries = BTreeMap::new();
+        loop {
+            let key_lexeme = self.is_next(self.current_lexeme.clone())?;
+            if let Token::CloseBrace = key_lexeme.token {
+                return Ok(DynType::Map(Map::new(entries)));
+            }
+            let key = self.parse_value()?;
+            let key = MapKey::from_value(&key).map_err(|_| {
+                let last = self.current_lexeme.as_ref().unwrap_or(&key_lexeme);
+                ParseError {
+                    kind: ParseErrorKind::InvalidMapKey,
+                    start: (key_lexeme.line, key_lexeme.line_char),
+                    end: (last.end_line, last.end_line_char),
+                }
+            })?;
+
+            let value_lexeme = self.next_lexeme()?;
+            let value_lexeme = self.is_next(value_lexeme)?;
+            if let Token::CloseBrace = value_lexeme.token {
+                unexpected_token(&value_lexeme)?;
+            }
+            entries.insert(key, self.parse_value()?);
+            self.next_lexeme()?;
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, ParseError> {
+        self.skip_datum_comments()?;
+        let current = self.is_next(self.current_lexeme.clone())?;
         let position = Some((current.line, current.line_char));
 
         // expression can begin from
@@ -78,13 +195,37 @@ impl<'a> Parser<'a> {
                     self.next();
                     DynType::Quoted(self.parse_value()?)
                 }
-                Token::OpenBracket => {
+                Token::Quasiquote => {
+                    self.next();
+                    DynType::Quasiquoted(self.parse_value()?)
+                }
+                Token::Unquote => {
                     self.next();
+                    DynType::Unquoted(self.parse_value()?)
+                }
+                Token::UnquoteSplicing => {
+                    self.next();
+                    DynType::UnquoteSpliced(self.parse_value()?)
+                }
+                Token::OpenBracket => {
+                    self.next_lexeme()?;
                     self.parse_list()?
                 }
+                Token::OpenBrace => {
+                    self.next_lexeme()?;
+                    self.parse_map()?
+                }
+                Token::OpenSquareBracket => {
+                    self.next_lexeme()?;
+                    self.parse_vector()?
+                }
+                Token::Bool(value) => DynType::Bool(*value),
+                Token::Integer(number) => DynType::Integer(*number),
+                Token::BigInteger(number) => DynType::BigInteger(number.clone()),
+                Token::Rational(number) => DynType::Rational(number.clone()),
                 Token::Number(number) => DynType::Number(*number),
                 Token::Str(string) => DynType::Str(string.clone()),
-                Token::Symbol(symbol) => DynType::Symbol(symbol.clone()),
+                Token::Symbol(symbol) => DynType::Symbol(*symbol),
 
                 // dots, close brackets, etc. are wrong begin tokens
                 _ => unexpected_token(&current)?,
@@ -93,16 +234,64 @@ impl<'a> Parser<'a> {
         ))
     }
 
-    fn parse(&mut self) -> Result<Vec<Value>, String> {
+    // Skips the rest of the broken top-level expression. It ends when its brackets are closed
+    // or when an open bracket is found at the beginning of a line, the last one is taken
+    // as the beginning of the next top-level expression.
+    fn synchronize(&mut self) {
+        while self.depth > 0 {
+            match self.next() {
+                Some(Lexeme {
+                    token: Token::OpenBracket,
+                    line_char: 1,
+                    ..
+                }) => {
+                    self.depth = 1;
+                    return;
+                }
+                Some(_) => {}
+                None => return,
+            }
+        }
+        self.depth = 0;
+        self.next();
+    }
+
+    fn parse(&mut self) -> (Vec<Value>, Vec<ParseError>) {
         let mut lists = Vec::new();
-        while let Some(_) = self.next() {
-            lists.push(self.parse_value()?);
+        let mut errors = Vec::new();
+        self.next();
+        while self.current_lexeme.is_some() {
+            // a comment could be the last thing in the file
+            let value = self
+                .skip_datum_comments()
+                .and_then(|_| match self.current_lexeme {
+                    Some(_) => self.parse_value().map(Some),
+                    None => Ok(None),
+                });
+            match value {
+                Ok(value) => {
+                    lists.extend(value);
+                    self.next();
+                }
+                Err(err) => {
+                    errors.push(err);
+                    self.synchronize();
+                }
+            }
         }
 
-        Ok(lists)
+        (lists, errors)
+    }
+}
+
+pub fn parse(lexemes: &mut dyn Iterator<Item = Lexeme>) -> Result<Vec<Value>, Vec<ParseError>> {
+    match Parser::new(lexemes).parse() {
+        (values, errors) if errors.is_empty() => Ok(values),
+        (_, errors) => Err(errors),
     }
 }
 
-pub fn parse(lexemes: &mut dyn Iterator<Item = Lexeme>) -> Result<Vec<Value>, String> {
+// the parsing continues after errors, so the tree contains every correct top-level expression
+pub fn parse_partial(lexemes: &mut dyn Iterator<Item = Lexeme>) -> (Vec<Value>, Vec<ParseError>) {
     Parser::new(lexemes).parse()
 }
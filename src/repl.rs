@@ -0,0 +1,176 @@
+use std::{
+    fs,
+    io::{stdin, stdout, BufRead, Write},
+    path::Path,
+};
+
+use yal::{
+    lexer::{self, LexErrorKind},
+    parser::{self, ParseErrorKind},
+    types::value::Value,
+    Error, Interpreter,
+};
+
+use crate::print_error;
+
+const PROMPT: &str = "yal> ";
+const CONTINUATION_PROMPT: &str = "...  ";
+
+const HELP: &str = ":load <file>  executes the file in the current session
+:env          prints the variables defined in the session
+:help         prints this message
+:quit         exits, Ctrl^D works too";
+
+// Reads the entry by the lexer and the parser. The entry is incomplete, so none is returned,
+// when a string, a comment or a list isn't closed yet.
+fn read_entry(text: &str) -> Option<Result<Vec<Value>, Error>> {
+    let lexemes = match lexer::lex(&mut text.chars()) {
+        Ok(lexemes) => lexemes,
+        Err(err) => {
+            return match err.kind {
+                LexErrorKind::UnterminatedString | LexErrorKind::UnterminatedComment => None,
+                _ => Some(Err(Error::Lex(err))),
+            }
+        }
+    };
+    match parser::parse(&mut lexemes.into_iter()) {
+        Ok(values) => Some(Ok(values)),
+        Err(errors)
+            if errors
+                .iter()
+                .all(|err| matches!(err.kind, ParseErrorKind::UnexpectedEndOfFile)) =>
+        {
+            None
+        }
+        Err(errors) => Some(Err(Error::Parse(errors))),
+    }
+}
+
+// the entry is compiled as a whole, but the result of every top-level expression is printed
+fn eval_entry(interpreter: &mut Interpreter, text: &str, values: Vec<Value>) {
+    if let Err(err) = interpreter.eval_each(values, |result| println!("{:#}", result.content)) {
+        print_error(&err, text);
+    }
+}
+
+fn eval_command(interpreter: &mut Interpreter, command: &str) -> bool {
+    let mut parts = command.splitn(2, char::is_whitespace);
+    match (
+        parts.next().unwrap_or_default(),
+        parts.next().map(str::trim),
+    ) {
+        (":quit", None) | (":q", None) => return false,
+        (":help", None) => println!("{}", HELP),
+        (":env", None) => {
+            for (name, value) in interpreter.globals() {
+                println!("{} = {}", name, value.content);
+            }
+        }
+        (":load", Some(filename)) if !filename.is_empty() => match fs::read_to_string(filename) {
+            Ok(text) => {
+                if let Err(err) = interpreter.eval_str_from_file(&text, Path::new(filename)) {
+                    print_error(&err, &text);
+                }
+            }
+            Err(err) => eprintln!("Cannot read {}, cause: {}", filename, err),
+        },
+        _ => eprintln!("Unknown command {}, try :help", command),
+    }
+    true
+}
+
+pub fn run() {
+    let mut interpreter = Interpreter::new();
+    let mut buffer = String::new();
+    let stdin = stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        print!(
+            "{}",
+            if buffer.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            }
+        );
+        stdout()
+            .flush()
+            .unwrap_or_else(|error| println!("Print error: {}", &error));
+
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(err)) => {
+                eprintln!("Cannot read from stdio, cause: {}", err);
+                break;
+            }
+            None => {
+                println!();
+                break;
+            }
+        };
+
+        if buffer.is_empty() && line.trim_start().starts_with(':') {
+            if !eval_command(&mut interpreter, line.trim()) {
+                break;
+            }
+            continue;
+        }
+
+        buffer.push_str(&line);
+        buffer.push('\n');
+        match read_entry(&buffer) {
+            Some(Ok(values)) => eval_entry(&mut interpreter, &buffer, values),
+            Some(Err(err)) => print_error(&err, &buffer),
+            None => continue,
+        }
+        buffer.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_complete(text: &str) -> bool {
+        read_entry(text).is_some()
+    }
+
+    #[test]
+    fn open_forms_are_incomplete() {
+        for text in [
+            "(+ 1",
+            "[1 {2",
+            "\"abc",
+            "\"\"\"abc\"\"",
+            "#| #| |#",
+            "'",
+            "#;",
+        ] {
+            assert!(!is_complete(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn closed_forms_are_complete() {
+        for text in [
+            "",
+            "(+ 1 2)",
+            "\"(\"",
+            "r\"\\\"",
+            "# (",
+            "#| ( #| ) |# |#",
+            "#; (a) 1",
+            "(+ 1 2) 3",
+        ] {
+            assert!(matches!(read_entry(text), Some(Ok(_))), "{}", text);
+        }
+    }
+
+    #[test]
+    fn wrong_forms_are_reported_at_once() {
+        assert!(matches!(read_entry(")"), Some(Err(Error::Parse(_)))));
+        assert!(matches!(read_entry(") (+ 1"), Some(Err(Error::Parse(_)))));
+        assert!(matches!(read_entry("0x"), Some(Err(Error::Lex(_)))));
+    }
+}
@@ -0,0 +1,67 @@
+use std::fmt::Display;
+
+// count of the arguments, functions with a rest parameter have no max
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arity {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    pub fn exact(count: usize) -> Arity {
+        Arity {
+            min: count,
+            max: Some(count),
+        }
+    }
+
+    pub fn range(min: usize, max: usize) -> Arity {
+        Arity {
+            min,
+            max: Some(max),
+        }
+    }
+
+    pub fn at_least(min: usize) -> Arity {
+        Arity { min, max: None }
+    }
+
+    pub fn any() -> Arity {
+        Arity::at_least(0)
+    }
+
+    pub fn accepts(self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+
+// `1 argument`, `2 to 3 arguments`, `at least 1 argument`
+impl Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let noun = match self.max {
+            Some(max) if max != self.min => "arguments",
+            _ if self.min == 1 => "argument",
+            _ => "arguments",
+        };
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{} {}", max, noun),
+            Some(max) => write!(f, "{} to {} {}", self.min, max, noun),
+            None => write!(f, "at least {} {}", self.min, noun),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_agrees_with_the_count() {
+        assert_eq!(Arity::exact(1).to_string(), "1 argument");
+        assert_eq!(Arity::exact(0).to_string(), "0 arguments");
+        assert_eq!(Arity::at_least(1).to_string(), "at least 1 argument");
+        assert_eq!(Arity::at_least(2).to_string(), "at least 2 arguments");
+        assert_eq!(Arity::range(0, 1).to_string(), "0 to 1 arguments");
+        assert_eq!(Arity::range(2, 3).to_string(), "2 to 3 arguments");
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    fmt::{Display, Formatter, Result},
+    path::PathBuf,
+    rc::Rc,
+};
+
+use crate::types::{map::MapKey, symbol::Symbol, value::Value};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Quote {
+    Quasiquote,
+    Unquote,
+    UnquoteSplicing,
+}
+
+// Instructions of the stack machine. Operands are taken from the stack of the current call,
+// variables of the call are kept in the slots of its environment.
+pub enum Instruction {
+    Constant(Value),
+    Pop,
+    GetLocal(usize),
+    SetLocal(usize),
+    // depth is the count of the functions between the current one and the owner of the slot
+    GetOuter(usize, usize),
+    SetOuter(usize, usize),
+    // slots of the module scope and of the builtins, names are kept for the listing
+    GetGlobal(usize, Symbol),
+    DefineGlobal(usize, Symbol),
+    SetGlobal(usize, Symbol),
+    GetBuiltin(usize, Symbol),
+    Jump(usize),
+    JumpIfFalse(usize),
+    // `and` and `or` keep the deciding value on the stack
+    JumpIfFalseOrPop(usize),
+    JumpIfTrueOrPop(usize),
+    Call(usize),
+    TailCall(usize),
+    Return,
+    Closure(Rc<Prototype>),
+    GetField(Symbol),
+    // items of a quasiquoted list, true marks the spliced ones, the tail of the list is the last
+    BuildList(Rc<[bool]>),
+    // values of a map literal are on the stack in the order of the keys
+    BuildMap(Rc<[MapKey]>),
+    BuildVector(usize),
+    Wrap(Quote),
+    Throw,
+    Try(Rc<TryBlock>),
+}
+
+// Blocks of a try form run in the environment of the call which contains them
+pub struct TryBlock {
+    pub body: Rc<Chunk>,
+    pub catch: Option<(usize, Rc<Chunk>)>,
+    pub finally: Option<Rc<Chunk>>,
+}
+
+pub struct Chunk {
+    pub code: Vec<Instruction>,
+    pub positions: Vec<Option<(u32, u32)>>,
+    pub file: Option<Rc<PathBuf>>,
+}
+
+impl Chunk {
+    pub fn new(file: Option<Rc<PathBuf>>) -> Self {
+        Self {
+            code: vec![],
+            positions: vec![],
+            file,
+        }
+    }
+}
+
+// Compiled function. Parameters take the first slots, then go the variables of its body.
+pub struct Prototype {
+    pub name: String,
+    pub params: usize,
+    pub rest: bool,
+    pub slots: usize,
+    pub chunk: Rc<Chunk>,
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Instruction::Constant(value) => write!(f, "Constant {}", value.content),
+            Instruction::Pop => write!(f, "Pop"),
+            Instruction::GetLocal(slot) => write!(f, "GetLocal {}", slot),
+            Instruction::SetLocal(slot) => write!(f, "SetLocal {}", slot),
+            Instruction::GetOuter(depth, slot) => write!(f, "GetOuter {} {}", depth, slot),
+            Instruction::SetOuter(depth, slot) => write!(f, "SetOuter {} {}", depth, slot),
+            Instruction::GetGlobal(slot, name) => write!(f, "GetGlobal {} {}", slot, name),
+            Instruction::DefineGlobal(slot, name) => write!(f, "DefineGlobal {} {}", slot, name),
+            Instruction::SetGlobal(slot, name) => write!(f, "SetGlobal {} {}", slot, name),
+            Instruction::GetBuiltin(slot, name) => write!(f, "GetBuiltin {} {}", slot, name),
+            Instruction::Jump(target) => write!(f, "Jump {}", target),
+            Instruction::JumpIfFalse(target) => write!(f, "JumpIfFalse {}", target),
+            Instruction::JumpIfFalseOrPop(target) => write!(f, "JumpIfFalseOrPop {}", target),
+            Instruction::JumpIfTrueOrPop(target) => write!(f, "JumpIfTrueOrPop {}", target),
+            Instruction::Call(count) => write!(f, "Call {}", count),
+            Instruction::TailCall(count) => write!(f, "TailCall {}", count),
+            Instruction::Return => write!(f, "Return"),
+            Instruction::Closure(prototype) => write!(f, "Closure {}", prototype.name),
+            Instruction::GetField(name) => write!(f, "GetField {}", name),
+            Instruction::BuildList(spliced) => {
+                write!(f, "BuildList")?;
+                for spliced in spliced.iter() {
+                    write!(f, " {}", if *spliced { "splice" } else { "item" })?;
+                }
+                Ok(())
+            }
+            Instruction::BuildMap(keys) => {
+                write!(f, "BuildMap")?;
+                for key in keys.iter() {
+                    write!(f, " {}", key)?;
+                }
+                Ok(())
+            }
+            Instruction::BuildVector(count) => write!(f, "BuildVector {}", count),
+            Instruction::Wrap(quote) => write!(f, "Wrap {:?}", quote),
+            Instruction::Throw => write!(f, "Throw"),
+            Instruction::Try(_) => write!(f, "Try"),
+        }
+    }
+}
+
+fn write_chunk(f: &mut Formatter<'_>, chunk: &Chunk, indent: usize) -> Result {
+    let padding = " ".repeat(indent);
+    for (index, instruction) in chunk.code.iter().enumerate() {
+        let position = match chunk.positions[index] {
+            Some((line, line_char)) => format!("{}-{}", line, line_char),
+            None => String::new(),
+        };
+        writeln!(
+            f,
+            "{}{:>4} {:>8}  {}",
+            padding, index, position, instruction
+        )?;
+    }
+
+    // nested code goes after the code which uses it
+    for (index, instruction) in chunk.code.iter().enumerate() {
+        match instruction {
+            Instruction::Closure(prototype) => {
+                writeln!(f)?;
+                write_prototype(f, prototype, indent + 2)?;
+            }
+            Instruction::Try(block) => {
+                writeln!(f)?;
+                writeln!(f, "{}  try at {}:", padding, index)?;
+                write_chunk(f, &block.body, indent + 2)?;
+                if let Some((slot, handler)) = &block.catch {
+                    writeln!(f, "{}  catch into slot {}:", padding, slot)?;
+                    write_chunk(f, handler, indent + 2)?;
+                }
+                if let Some(cleanup) = &block.finally {
+                    writeln!(f, "{}  finally:", padding)?;
+                    write_chunk(f, cleanup, indent + 2)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn write_prototype(f: &mut Formatter<'_>, prototype: &Prototype, indent: usize) -> Result {
+    writeln!(
+        f,
+        "{}function {} ({} params{}, {} slots):",
+        " ".repeat(indent),
+        prototype.name,
+        prototype.params,
+        if prototype.rest { " and rest" } else { "" },
+        prototype.slots
+    )?;
+    write_chunk(f, &prototype.chunk, indent)
+}
+
+impl Display for Prototype {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write_prototype(f, self, 0)
+    }
+}
@@ -0,0 +1,663 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    rc::Rc,
+};
+
+use crate::types::{
+    exception::{Exception, Frame},
+    list::{List, ListItem},
+    symbol::Symbol,
+    value::Value,
+    DynType,
+};
+
+use super::{
+    bytecode::{Chunk, Instruction, Prototype, Quote},
+    macros::Macro,
+    scope::{ScopeRef, ScopeState},
+    special_forms::SpecialForms,
+};
+
+enum Binding {
+    Slot(usize),
+    // macros of the blocks exist only during the compilation
+    Macro(Rc<Macro>),
+}
+
+pub enum Variable {
+    Local(usize),
+    Outer(usize, usize),
+    // slots of the module scope and of the builtins
+    Global(usize, Symbol),
+    Builtin(usize, Symbol),
+    Macro(Rc<Macro>),
+}
+
+// use of a global variable which is not defined yet
+struct Reference {
+    slot: usize,
+    name: Symbol,
+    position: Option<(u32, u32)>,
+    is_set: bool,
+    // uses in the functions could be defined by the later top-level forms
+    deferred: bool,
+}
+
+struct FunctionState {
+    blocks: Vec<HashMap<Symbol, Binding>>,
+    slots: usize,
+    // blocks of try forms are compiled into their own chunks
+    chunks: Vec<Chunk>,
+}
+
+// Turns the parsed values into the code of the stack machine. Every symbol is resolved
+// during the compilation: variables of the functions and `do` blocks get (depth, slot) addresses,
+// the rest get slots of the module scope or of the builtins. The compiler is kept for the whole
+// file or REPL entry, so functions could use variables which are defined below them.
+pub struct Compiler {
+    special_forms: Rc<SpecialForms>,
+    globals: ScopeRef,
+    file: Option<Rc<PathBuf>>,
+    functions: Vec<FunctionState>,
+    // global slots which are defined by the compiled code, but the code isn't run yet
+    defined: HashSet<usize>,
+    references: Vec<Reference>,
+}
+
+impl Compiler {
+    pub fn new(special_forms: Rc<SpecialForms>, globals: ScopeRef) -> Self {
+        let file = globals
+            .borrow()
+            .module()
+            .and_then(|module| module.path.clone());
+        Self {
+            special_forms,
+            globals,
+            file,
+            functions: vec![],
+            defined: HashSet::new(),
+            references: vec![],
+        }
+    }
+
+    // Checks the top-level form before it runs: the variables which it uses outside of functions
+    // must be defined by it or before it. Uses in the functions are checked by `finish`.
+    pub fn check(&mut self) -> Result<(), Vec<Exception>> {
+        let (deferred, immediate) = self
+            .references
+            .drain(..)
+            .partition(|reference| reference.deferred);
+        self.references = deferred;
+        self.undefined(&immediate)
+    }
+
+    // Checks that every used global variable is defined by the compiled code or before it.
+    // Every undefined reference is reported with its own position.
+    pub fn finish(&self) -> Result<(), Vec<Exception>> {
+        self.undefined(&self.references)
+    }
+
+    fn undefined(&self, references: &[Reference]) -> Result<(), Vec<Exception>> {
+        let globals = self.globals.borrow();
+        let errors: Vec<_> = references
+            .iter()
+            .filter(|reference| {
+                !self.defined.contains(&reference.slot) && !globals.is_defined(reference.slot)
+            })
+            .map(|reference| {
+                let message = if reference.is_set {
+                    format!("variable {} is undefined and cannot be set", reference.name)
+                } else {
+                    format!("variable {} is undefined", reference.name)
+                };
+                self.error(message, reference.position)
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    // references of a compiler for the same scope are checked together with the own ones,
+    // the other compiler is used for a function
+    pub fn merge(&mut self, other: Compiler) {
+        self.references
+            .extend(other.references.into_iter().map(|reference| Reference {
+                deferred: true,
+                ..reference
+            }));
+    }
+
+    pub fn special_forms(&self) -> Rc<SpecialForms> {
+        self.special_forms.clone()
+    }
+
+    pub fn globals(&self) -> ScopeRef {
+        self.globals.clone()
+    }
+
+    pub fn frame(&self, position: Option<(u32, u32)>) -> Frame {
+        Frame {
+            file: self.file.clone(),
+            position,
+        }
+    }
+
+    pub fn error(&self, message: String, position: Option<(u32, u32)>) -> Exception {
+        Exception {
+            thrown_object: Value::new(DynType::Str(message), None),
+            traceback: vec![self.frame(position)],
+            previous_exception: None,
+        }
+    }
+
+    fn function_state(&mut self) -> &mut FunctionState {
+        self.functions.last_mut().unwrap()
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        self.function_state().chunks.last_mut().unwrap()
+    }
+
+    // returns the index of the instruction
+    pub fn emit(&mut self, instruction: Instruction, position: Option<(u32, u32)>) -> usize {
+        let chunk = self.chunk();
+        chunk.code.push(instruction);
+        chunk.positions.push(position);
+        chunk.code.len() - 1
+    }
+
+    // points the jump to the next instruction
+    pub fn patch_jump(&mut self, index: usize) {
+        let chunk = self.chunk();
+        let target = chunk.code.len();
+        match &mut chunk.code[index] {
+            Instruction::Jump(jump_target)
+            | Instruction::JumpIfFalse(jump_target)
+            | Instruction::JumpIfFalseOrPop(jump_target)
+            | Instruction::JumpIfTrueOrPop(jump_target) => *jump_target = target,
+            _ => panic!("instruction {} is not a jump", index),
+        }
+    }
+
+    pub fn begin_block(&mut self) {
+        self.function_state().blocks.push(HashMap::new());
+    }
+
+    pub fn end_block(&mut self) {
+        self.function_state().blocks.pop();
+    }
+
+    // compiles the code into a separate chunk which shares the slots of the current function
+    pub fn block_chunk<F>(&mut self, compile: F) -> Result<Rc<Chunk>, Exception>
+    where
+        F: FnOnce(&mut Compiler) -> Result<(), Exception>,
+    {
+        let file = self.file.clone();
+        self.function_state().chunks.push(Chunk::new(file));
+        let result = compile(self);
+        self.emit(Instruction::Return, None);
+        let chunk = self.function_state().chunks.pop().unwrap();
+        result.map(|_| Rc::new(chunk))
+    }
+
+    fn bind(
+        &mut self,
+        name: Symbol,
+        binding: Binding,
+        position: Option<(u32, u32)>,
+    ) -> Result<(), Exception> {
+        let block = match self.function_state().blocks.last_mut() {
+            Some(block) => block,
+            None => {
+                return Err(self.error("there is no block for the variable".to_string(), position))
+            }
+        };
+        if block.contains_key(&name) {
+            return Err(self.error(format!("variable {} already exists", name), position));
+        }
+        block.insert(name, binding);
+        Ok(())
+    }
+
+    // new slot in the innermost block
+    pub fn declare_local(
+        &mut self,
+        name: Symbol,
+        position: Option<(u32, u32)>,
+    ) -> Result<usize, Exception> {
+        let slot = self.function_state().slots;
+        self.bind(name, Binding::Slot(slot), position)?;
+        self.function_state().slots += 1;
+        Ok(slot)
+    }
+
+    pub fn is_global_scope(&self) -> bool {
+        self.functions.len() == 1 && self.functions[0].blocks.is_empty()
+    }
+
+    // Declares the variable and returns the instruction which moves a value from the stack into it.
+    // Outside of functions and blocks variables are defined in the global scope.
+    pub fn define(
+        &mut self,
+        name: Symbol,
+        position: Option<(u32, u32)>,
+    ) -> Result<Instruction, Exception> {
+        if self.is_global_scope() {
+            let slot = self.declare_global(name, position)?;
+            Ok(Instruction::DefineGlobal(slot, name))
+        } else {
+            Ok(Instruction::SetLocal(self.declare_local(name, position)?))
+        }
+    }
+
+    fn declare_global(
+        &mut self,
+        name: Symbol,
+        position: Option<(u32, u32)>,
+    ) -> Result<usize, Exception> {
+        let slot = self.globals.borrow_mut().slot(name);
+        if self.globals.borrow().is_defined(slot) || !self.defined.insert(slot) {
+            return Err(self.error(format!("variable {} already exists", name), position));
+        }
+        Ok(slot)
+    }
+
+    pub fn define_macro(
+        &mut self,
+        name: Symbol,
+        macro_value: Macro,
+        position: Option<(u32, u32)>,
+    ) -> Result<(), Exception> {
+        let macro_value = Rc::new(macro_value);
+        if self.is_global_scope() {
+            let slot = self.declare_global(name, position)?;
+            self.globals
+                .borrow_mut()
+                .define(slot, Value::new(DynType::Macro(macro_value), None))
+        } else {
+            self.bind(name, Binding::Macro(macro_value), position)
+        }
+    }
+
+    pub fn resolve(
+        &mut self,
+        name: Symbol,
+        position: Option<(u32, u32)>,
+        is_set: bool,
+    ) -> Variable {
+        for (depth, function) in self.functions.iter().rev().enumerate() {
+            for block in function.blocks.iter().rev() {
+                match block.get(&name) {
+                    Some(Binding::Slot(slot)) if depth == 0 => return Variable::Local(*slot),
+                    Some(Binding::Slot(slot)) => return Variable::Outer(depth, *slot),
+                    Some(Binding::Macro(macro_value)) => {
+                        return Variable::Macro(macro_value.clone())
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        let slot = self.globals.borrow().find(name);
+        if let Some(slot) = slot {
+            if self.defined.contains(&slot) || self.globals.borrow().is_defined(slot) {
+                return Variable::Global(slot, name);
+            }
+        }
+        let builtins = self.globals.borrow().outer_scope.clone();
+        if let Some(builtins) = builtins {
+            let builtins = builtins.borrow();
+            if let Some(slot) = builtins
+                .find(name)
+                .filter(|slot| builtins.is_defined(*slot))
+            {
+                return Variable::Builtin(slot, name);
+            }
+        }
+
+        // the variable could be defined later by the compiled code
+        let slot = self.globals.borrow_mut().slot(name);
+        self.references.push(Reference {
+            slot,
+            name,
+            position,
+            is_set,
+            deferred: self.functions.len() > 1,
+        });
+        Variable::Global(slot, name)
+    }
+
+    // local binding of the name, globals are not touched
+    fn local(&self, name: Symbol) -> Option<&Binding> {
+        self.functions
+            .iter()
+            .rev()
+            .flat_map(|function| function.blocks.iter().rev())
+            .find_map(|block| block.get(&name))
+    }
+
+    // macro which is called by the form, macros of the global scope must be defined before the form is compiled
+    pub fn macro_of(&self, form: &Value) -> Option<Rc<Macro>> {
+        let pair = match &*form.content {
+            DynType::Pair(pair) => pair,
+            _ => return None,
+        };
+        let symbol = match &*pair.left.content {
+            DynType::Symbol(symbol) if !self.special_forms.contains_key(symbol) => *symbol,
+            _ => return None,
+        };
+        match self.local(symbol) {
+            Some(Binding::Macro(macro_value)) => Some(macro_value.clone()),
+            Some(Binding::Slot(_)) => None,
+            None => match self.globals.borrow().variable(symbol) {
+                Ok(value) => match &*value.content {
+                    DynType::Macro(macro_value) => Some(macro_value.clone()),
+                    _ => None,
+                },
+                Err(_) => None,
+            },
+        }
+    }
+
+    pub fn compile_top_level(&mut self, value: Value) -> Result<Rc<Prototype>, Exception> {
+        self.functions.push(FunctionState {
+            blocks: vec![],
+            slots: 0,
+            chunks: vec![Chunk::new(self.file.clone())],
+        });
+        let result = self.compile(value, ScopeState::Global, false);
+        self.emit(Instruction::Return, None);
+        let mut function = self.functions.pop().unwrap();
+        result?;
+        Ok(Rc::new(Prototype {
+            name: "top-level".to_string(),
+            params: 0,
+            rest: false,
+            slots: function.slots,
+            chunk: Rc::new(function.chunks.pop().unwrap()),
+        }))
+    }
+
+    // params are a list of symbols, a symbol after the dot gets the list of the rest arguments
+    pub fn function(
+        &mut self,
+        name: &str,
+        params: Value,
+        body: Value,
+        position: Option<(u32, u32)>,
+    ) -> Result<Rc<Prototype>, Exception> {
+        self.functions.push(FunctionState {
+            blocks: vec![HashMap::new()],
+            slots: 0,
+            chunks: vec![Chunk::new(self.file.clone())],
+        });
+        let result = self.compile_function(params, body, position);
+        let mut function = self.functions.pop().unwrap();
+        let (params, rest) = result?;
+        Ok(Rc::new(Prototype {
+            name: name.to_string(),
+            params,
+            rest,
+            slots: function.slots,
+            chunk: Rc::new(function.chunks.pop().unwrap()),
+        }))
+    }
+
+    fn compile_function(
+        &mut self,
+        params: Value,
+        body: Value,
+        position: Option<(u32, u32)>,
+    ) -> Result<(usize, bool), Exception> {
+        let mut params_list = List::new(params);
+        let mut count = 0;
+        while let ListItem::Middle(param) = params_list.next() {
+            self.declare_local(param.content.to_symbol()?, position)?;
+            count += 1;
+        }
+        let rest = match params_list.next() {
+            ListItem::Last(param) => {
+                self.declare_local(param.content.to_symbol()?, position)?;
+                true
+            }
+            _ => false,
+        };
+
+        self.compile(body, ScopeState::Expression, true)?;
+        self.emit(Instruction::Return, None);
+        Ok((count, rest))
+    }
+
+    pub fn compile(
+        &mut self,
+        value: Value,
+        scope_state: ScopeState,
+        tail: bool,
+    ) -> Result<(), Exception> {
+        match &*value.content {
+            DynType::Pair(_) => self.compile_call(value, scope_state, tail)?,
+            DynType::Symbol(symbol) => {
+                let instruction = match self.resolve(*symbol, value.position, false) {
+                    Variable::Local(slot) => Instruction::GetLocal(slot),
+                    Variable::Outer(depth, slot) => Instruction::GetOuter(depth, slot),
+                    Variable::Global(slot, name) => Instruction::GetGlobal(slot, name),
+                    Variable::Builtin(slot, name) => Instruction::GetBuiltin(slot, name),
+                    Variable::Macro(macro_value) => {
+                        Instruction::Constant(Value::new(DynType::Macro(macro_value), None))
+                    }
+                };
+                self.emit(instruction, value.position);
+            }
+            DynType::Quoted(quoted) => {
+                self.emit(Instruction::Constant(quoted.clone()), value.position);
+            }
+            DynType::Quasiquoted(template) => self.compile_template(template, 1)?,
+            DynType::Map(map) if !is_constant(&value) => {
+                let mut keys = vec![];
+                for (key, item) in map.entries() {
+                    self.compile(item.clone(), ScopeState::Expression, false)?;
+                    keys.push(key.clone());
+                }
+                self.emit(Instruction::BuildMap(keys.into()), value.position);
+            }
+            DynType::Vector(vector) if !is_constant(&value) => {
+                for item in vector.iter() {
+                    self.compile(item.clone(), ScopeState::Expression, false)?;
+                }
+                self.emit(Instruction::BuildVector(vector.len()), value.position);
+            }
+            DynType::Unquoted(_) | DynType::UnquoteSpliced(_) => {
+                return Err(self.error(
+                    "Unquote is allowed only inside quasiquote".to_string(),
+                    value.position,
+                ))
+            }
+            _ => {
+                self.emit(Instruction::Constant(value.clone()), value.position);
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_call(
+        &mut self,
+        value: Value,
+        scope_state: ScopeState,
+        tail: bool,
+    ) -> Result<(), Exception> {
+        let pair = value.content.to_pair()?;
+        if let DynType::Symbol(symbol) = &*pair.left.content {
+            if let Some(special_form) = self.special_forms.clone().get(symbol) {
+                return special_form.compile(
+                    self,
+                    scope_state,
+                    pair.right.clone(),
+                    value.position,
+                    tail,
+                );
+            }
+        }
+
+        if let Some(macro_value) = self.macro_of(&value) {
+            let expansion = macro_value
+                .expand(pair.right.clone(), value.position)
+                .map_err(|mut err| {
+                    err.traceback.push(self.frame(value.position));
+                    err
+                })?;
+            return self.compile(expansion, scope_state, tail);
+        }
+
+        self.compile(pair.left.clone(), ScopeState::Expression, false)?;
+        let mut args = List::new(pair.right.clone());
+        let mut count = 0;
+        while let ListItem::Middle(arg) = args.next() {
+            self.compile(arg, ScopeState::Expression, false)?;
+            count += 1;
+        }
+        if let ListItem::Last(rest) = args.next() {
+            return Err(self.error(
+                format!(
+                    "Arguments must be a list, found {} after the dot",
+                    rest.content
+                ),
+                value.position,
+            ));
+        }
+
+        if tail {
+            self.emit(Instruction::TailCall(count), value.position);
+        } else {
+            self.emit(Instruction::Call(count), value.position);
+        }
+        Ok(())
+    }
+
+    // Builds the data of a quasiquoted template. Depth counts nested quasiquotes,
+    // only unquotes of the outermost one are calculated.
+    fn compile_template(&mut self, template: &Value, depth: usize) -> Result<(), Exception> {
+        if !has_unquote(template, depth) {
+            self.emit(Instruction::Constant(template.clone()), template.position);
+            return Ok(());
+        }
+        match &*template.content {
+            DynType::Unquoted(unquoted) if depth == 1 => {
+                self.compile(unquoted.clone(), ScopeState::Expression, false)?
+            }
+            DynType::Unquoted(unquoted) => {
+                self.compile_template(unquoted, depth - 1)?;
+                self.emit(Instruction::Wrap(Quote::Unquote), template.position);
+            }
+            DynType::UnquoteSpliced(unquoted) if depth > 1 => {
+                self.compile_template(unquoted, depth - 1)?;
+                self.emit(Instruction::Wrap(Quote::UnquoteSplicing), template.position);
+            }
+            DynType::UnquoteSpliced(_) => {
+                return Err(self.error(
+                    "Unquote-splicing is allowed only inside a list".to_string(),
+                    template.position,
+                ))
+            }
+            DynType::Quasiquoted(quasiquoted) => {
+                self.compile_template(quasiquoted, depth + 1)?;
+                self.emit(Instruction::Wrap(Quote::Quasiquote), template.position);
+            }
+            DynType::Pair(_) => {
+                let mut spliced = vec![];
+                let mut list = List::new(template.clone());
+                while let ListItem::Middle(item) = list.next() {
+                    match &*item.content {
+                        DynType::UnquoteSpliced(unquoted) if depth == 1 => {
+                            self.compile(unquoted.clone(), ScopeState::Expression, false)?;
+                            spliced.push(true);
+                        }
+                        _ => {
+                            self.compile_template(&item, depth)?;
+                            spliced.push(false);
+                        }
+                    }
+                }
+                match list.next() {
+                    ListItem::Last(rest) => self.compile_template(&rest, depth)?,
+                    _ => {
+                        self.emit(
+                            Instruction::Constant(Value::new(DynType::Nil, None)),
+                            template.position,
+                        );
+                    }
+                }
+                self.emit(Instruction::BuildList(spliced.into()), template.position);
+            }
+            _ => {
+                self.emit(Instruction::Constant(template.clone()), template.position);
+            }
+        }
+        Ok(())
+    }
+}
+
+// literals which are the same after the calculation
+fn is_constant(value: &Value) -> bool {
+    match &*value.content {
+        DynType::Symbol(_)
+        | DynType::Pair(_)
+        | DynType::Quoted(_)
+        | DynType::Quasiquoted(_)
+        | DynType::Unquoted(_)
+        | DynType::UnquoteSpliced(_) => false,
+        DynType::Map(map) => map.entries().all(|(_, item)| is_constant(item)),
+        DynType::Vector(vector) => vector.iter().all(is_constant),
+        _ => true,
+    }
+}
+
+fn has_unquote(template: &Value, depth: usize) -> bool {
+    match &*template.content {
+        DynType::Unquoted(_) | DynType::UnquoteSpliced(_) if depth == 1 => true,
+        DynType::Unquoted(unquoted) | DynType::UnquoteSpliced(unquoted) => {
+            has_unquote(unquoted, depth - 1)
+        }
+        DynType::Quasiquoted(quasiquoted) => has_unquote(quasiquoted, depth + 1),
+        DynType::Pair(pair) => has_unquote(&pair.left, depth) || has_unquote(&pair.right, depth),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Interpreter};
+
+    #[test]
+    fn every_undefined_variable_is_reported() {
+        let code = "(println a)\n(def (f) (+ b a))\n(set c 1)\n(let b 2)";
+        let errors = match Interpreter::new().compile_str(code) {
+            Err(Error::Undefined(errors)) => errors,
+            Err(err) => panic!("expected undefined variables, given {}", err),
+            Ok(_) => panic!("expected undefined variables"),
+        };
+        let reported: Vec<_> = errors
+            .iter()
+            .map(|err| {
+                (
+                    err.thrown_object.content.to_string(),
+                    err.traceback[0].position,
+                )
+            })
+            .collect();
+        assert_eq!(
+            reported,
+            [
+                ("variable a is undefined".to_string(), Some((1, 10))),
+                ("variable a is undefined".to_string(), Some((2, 15))),
+                (
+                    "variable c is undefined and cannot be set".to_string(),
+                    Some((3, 1))
+                ),
+            ]
+        );
+    }
+}
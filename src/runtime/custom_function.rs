@@ -8,77 +8,90 @@ use crate::types::{
     DynType,
 };
 
-use super::{
-    calculators::calculate,
-    scope::{Scope, ScopeRef, ScopeState},
-    special_forms::SpecialForms,
-};
+use super::{bytecode::Prototype, scope::ScopeRef, vm};
+
+// Slots of a function call. The outer environment belongs to the call
+// where the function has been created.
+pub struct Env {
+    pub slots: RefCell<Vec<Value>>,
+    pub outer: Option<Rc<Env>>,
+}
+
+impl Env {
+    pub fn new(slots: usize, outer: Option<Rc<Env>>) -> Self {
+        Self {
+            slots: RefCell::new(vec![Value::new(DynType::Nil, None); slots]),
+            outer,
+        }
+    }
+
+    pub fn outer(self: &Rc<Self>, depth: usize) -> Rc<Env> {
+        let mut env = self.clone();
+        for _ in 0..depth {
+            env = env.outer.clone().unwrap();
+        }
+        env
+    }
+}
 
 pub struct CustomFunction {
-    expression: Value,
-    outer_scope: ScopeRef,
-    arg_symbols: Value,
+    prototype: Rc<Prototype>,
+    env: Option<Rc<Env>>,
+    globals: ScopeRef,
 }
 
 impl CustomFunction {
-    pub fn new(expression: Value, outer_scope: ScopeRef, arg_symbols: Value) -> Self {
+    pub fn new(prototype: Rc<Prototype>, env: Option<Rc<Env>>, globals: ScopeRef) -> Self {
         Self {
-            expression,
-            outer_scope,
-            arg_symbols,
+            prototype,
+            env,
+            globals,
         }
     }
 
-    pub fn call(&self, special_forms: Rc<SpecialForms>, args: Value) -> Result<Value, Exception> {
-        let scope = Rc::new(RefCell::new(Scope::new(Some(self.outer_scope.clone()))));
-        self.define_parameters_in_scope(scope.clone(), args)?;
-        calculate(
-            special_forms.clone(),
-            scope,
-            ScopeState::Expression,
-            self.expression.clone(),
-        )
+    pub fn call(&self, args: Value) -> Result<Value, Exception> {
+        let mut list = List::new(args);
+        let mut items = vec![];
+        while let ListItem::Middle(item) = list.next() {
+            items.push(item);
+        }
+        list.next().to_end()?;
+        vm::call(self, items)
     }
 
-    fn define_parameters_in_scope(&self, scope: ScopeRef, args: Value) -> Result<(), Exception> {
-        let mut defined_args = List::new(self.arg_symbols.clone());
-        let mut got_args = List::new(args);
-        let prepared_exception = Err(Exception {
-            thrown_object: Value::new(
-                DynType::Str(format!("Arguments count error, given more or less")),
-                None,
-            ),
-            traceback: vec![],
-            previous_exception: None,
-        });
+    pub fn prototype(&self) -> Rc<Prototype> {
+        self.prototype.clone()
+    }
 
-        while let ListItem::Middle(value) = defined_args.next() {
-            let next = match got_args.next().to_middle() {
-                Ok(v) => v,
-                Err(_) => return prepared_exception,
-            };
-            scope
-                .borrow_mut()
-                .define_variable(value.content.to_symbol()?, next)?;
+    pub fn globals(&self) -> ScopeRef {
+        self.globals.clone()
+    }
+
+    // creates the environment of a call with the arguments in the first slots
+    pub fn prepare_env(&self, mut args: Vec<Value>) -> Result<Rc<Env>, Exception> {
+        let prototype = &self.prototype;
+        if args.len() < prototype.params || (args.len() > prototype.params && !prototype.rest) {
+            return Err(Exception::from("Arguments count error, given more or less"));
+        }
+
+        let mut rest = Value::new(DynType::Nil, None);
+        for arg in args.drain(prototype.params..).rev() {
+            rest = Value::new(
+                DynType::Pair(DotPair {
+                    left: arg,
+                    right: rest,
+                }),
+                None,
+            );
         }
-        match (defined_args.next(), got_args.next()) {
-            (ListItem::Last(last), ListItem::Middle(got))
-            | (ListItem::Last(last), ListItem::Last(got)) => {
-                scope.borrow_mut().define_variable(
-                    last.content.to_symbol()?,
-                    Value::new(
-                        DynType::Pair(DotPair {
-                            left: got,
-                            right: got_args.current_value,
-                        }),
-                        None,
-                    ),
-                )?;
-            }
-            (ListItem::End, ListItem::End) => {}
-            _ => return prepared_exception,
+        if prototype.rest {
+            args.push(rest);
         }
+        args.resize(prototype.slots, Value::new(DynType::Nil, None));
 
-        Ok(())
+        Ok(Rc::new(Env {
+            slots: RefCell::new(args),
+            outer: self.env.clone(),
+        }))
     }
 }
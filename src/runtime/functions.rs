@@ -8,12 +8,17 @@ use std::{
 use crate::types::{
     dot_pair::DotPair,
     exception::Exception,
-    list::{List, ListItem},
+    list::{list_of, List, ListItem},
+    map::MapKey,
+    number::{self, Rounding},
     struct_declare::Struct,
+    symbol::Symbol,
     value::Value,
     DynType,
 };
 
+use super::{arity::Arity, macros::gensym};
+
 fn lang_new(args: Value) -> Result<Value, Exception> {
     let mut list = List::new(args);
     let struct_type = list.next().to_middle()?.content.to_struct_declare()?;
@@ -29,7 +34,12 @@ fn lang_apply(args: Value) -> Result<Value, Exception> {
     let closure = list.next().to_middle()?.content.to_closure()?;
     let args = list.next().to_middle()?;
     list.next().to_end()?;
-    closure(args)
+    match &*args.content {
+        DynType::Vector(vector) => {
+            closure(list_of(vector.to_vec(), Value::new(DynType::Nil, None)))
+        }
+        _ => closure(args),
+    }
 }
 
 fn lang_input(args: Value) -> Result<Value, Exception> {
@@ -64,7 +74,7 @@ fn lang_println(arg: Value) -> Result<Value, Exception> {
 
 fn lang_print(arg: Value) -> Result<Value, Exception> {
     let mut list = List::new(arg);
-    
+
     while let ListItem::Middle(item) = list.next() {
         print!("{}", item.content);
     }
@@ -78,59 +88,99 @@ fn lang_print(arg: Value) -> Result<Value, Exception> {
 
 fn lang_num_add(args: Value) -> Result<Value, Exception> {
     let mut list = List::new(args);
-    let mut accum = 0.0;
+    let mut accum = DynType::Integer(0);
 
     while let ListItem::Middle(value) = list.next() {
-        accum += value.content.to_number()?;
+        accum = number::add(&accum, &value.content)?;
     }
     list.next().to_end()?;
-    Ok(Value::new(DynType::Number(accum), None))
+    Ok(Value::new(accum, None))
 }
 
 fn lang_num_mul(args: Value) -> Result<Value, Exception> {
     let mut list = List::new(args);
-    let mut accum = 1.0;
+    let mut accum = DynType::Integer(1);
 
     while let ListItem::Middle(value) = list.next() {
-        accum *= value.content.to_number()?;
+        accum = number::mul(&accum, &value.content)?;
     }
     list.next().to_end()?;
-    Ok(Value::new(DynType::Number(accum), None))
+    Ok(Value::new(accum, None))
 }
 
 fn lang_num_sub(args: Value) -> Result<Value, Exception> {
     let mut list = List::new(args);
-    let mut accum = list.next().to_middle()?.content.to_number()?;
+    let first = list.next().to_middle()?;
 
     let next = list.next();
     if let ListItem::End = next {
-        return Ok(Value::new(DynType::Number(-accum), None));
+        return Ok(Value::new(number::negate(&first.content)?, None));
     }
-    accum -= next.to_middle()?.content.to_number()?;
+    let mut accum = number::sub(&first.content, &next.to_middle()?.content)?;
     while let ListItem::Middle(value) = list.next() {
-        accum -= value.content.to_number()?;
+        accum = number::sub(&accum, &value.content)?;
     }
     list.next().to_end()?;
-    Ok(Value::new(DynType::Number(accum), None))
+    Ok(Value::new(accum, None))
 }
 
 fn lang_num_div(args: Value) -> Result<Value, Exception> {
     let mut list = List::new(args);
-    let mut accum = list.next().to_middle()?.content.to_number()?;
+    let first = list.next().to_middle()?;
+    let mut accum = number::div(&first.content, &DynType::Integer(1))?;
 
     while let ListItem::Middle(value) = list.next() {
-        accum /= value.content.to_number()?;
+        accum = number::div(&accum, &value.content)?;
     }
     list.next().to_end()?;
-    Ok(Value::new(DynType::Number(accum), None))
+    Ok(Value::new(accum, None))
 }
 
 fn lang_num_mod(args: Value) -> Result<Value, Exception> {
     let mut list = List::new(args);
-    let x = list.next().to_middle()?.content.to_number()?;
-    let y = list.next().to_middle()?.content.to_number()?;
+    let x = list.next().to_middle()?;
+    let y = list.next().to_middle()?;
+    list.next().to_end()?;
+    Ok(Value::new(number::modulo(&x.content, &y.content)?, None))
+}
+
+fn lang_int_div(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let x = list.next().to_middle()?;
+    let y = list.next().to_middle()?;
+    list.next().to_end()?;
+    Ok(Value::new(number::floor_div(&x.content, &y.content)?, None))
+}
+
+fn lang_floor(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let x = list.next().to_middle()?;
+    list.next().to_end()?;
+    Ok(Value::new(
+        number::round_with(&x.content, Rounding::Floor)?,
+        None,
+    ))
+}
+
+fn lang_ceil(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let x = list.next().to_middle()?;
+    list.next().to_end()?;
+    Ok(Value::new(
+        number::round_with(&x.content, Rounding::Ceil)?,
+        None,
+    ))
+}
+
+// halves are rounded away from zero
+fn lang_round(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let x = list.next().to_middle()?;
     list.next().to_end()?;
-    Ok(Value::new(DynType::Number(x % y), None))
+    Ok(Value::new(
+        number::round_with(&x.content, Rounding::Round)?,
+        None,
+    ))
 }
 
 fn lang_equals(args: Value) -> Result<Value, Exception> {
@@ -138,11 +188,11 @@ fn lang_equals(args: Value) -> Result<Value, Exception> {
     let first = list.next().to_middle()?;
     while let ListItem::Middle(current) = list.next() {
         if first.content != current.content {
-            return Ok(Value::new(DynType::Nil, None));
+            return Ok(Value::new(DynType::Bool(false), None));
         }
     }
     list.next().to_end()?;
-    Ok(Value::new(DynType::Number(1.0), None))
+    Ok(Value::new(DynType::Bool(true), None))
 }
 
 fn lang_not_equals(args: Value) -> Result<Value, Exception> {
@@ -150,11 +200,18 @@ fn lang_not_equals(args: Value) -> Result<Value, Exception> {
     let first = list.next().to_middle()?;
     while let ListItem::Middle(current) = list.next() {
         if first.content == current.content {
-            return Ok(Value::new(DynType::Nil, None));
+            return Ok(Value::new(DynType::Bool(false), None));
         }
     }
     list.next().to_end()?;
-    Ok(Value::new(DynType::Number(1.0), None))
+    Ok(Value::new(DynType::Bool(true), None))
+}
+
+fn lang_not(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let value = list.next().to_middle()?;
+    list.next().to_end()?;
+    Ok(Value::new(DynType::Bool(!value.content.is_truthy()), None))
 }
 
 fn lang_greater_than(args: Value) -> Result<Value, Exception> {
@@ -163,8 +220,8 @@ fn lang_greater_than(args: Value) -> Result<Value, Exception> {
     while let ListItem::Middle(current) = list.next() {
         match previous.content.partial_cmp(&current.content) {
             Some(cmp) => match cmp {
-                std::cmp::Ordering::Less => return Ok(Value::new(DynType::Nil, None)),
-                std::cmp::Ordering::Equal => return Ok(Value::new(DynType::Nil, None)),
+                std::cmp::Ordering::Less => return Ok(Value::new(DynType::Bool(false), None)),
+                std::cmp::Ordering::Equal => return Ok(Value::new(DynType::Bool(false), None)),
                 std::cmp::Ordering::Greater => {}
             },
             None => {
@@ -185,7 +242,7 @@ fn lang_greater_than(args: Value) -> Result<Value, Exception> {
         previous = current;
     }
     list.next().to_end()?;
-    Ok(Value::new(DynType::Number(1.0), None))
+    Ok(Value::new(DynType::Bool(true), None))
 }
 
 fn lang_greater_than_or_equals(args: Value) -> Result<Value, Exception> {
@@ -194,7 +251,7 @@ fn lang_greater_than_or_equals(args: Value) -> Result<Value, Exception> {
     while let ListItem::Middle(current) = list.next() {
         match previous.content.partial_cmp(&current.content) {
             Some(cmp) => match cmp {
-                std::cmp::Ordering::Less => return Ok(Value::new(DynType::Nil, None)),
+                std::cmp::Ordering::Less => return Ok(Value::new(DynType::Bool(false), None)),
                 std::cmp::Ordering::Equal => {}
                 std::cmp::Ordering::Greater => {}
             },
@@ -216,7 +273,7 @@ fn lang_greater_than_or_equals(args: Value) -> Result<Value, Exception> {
         previous = current;
     }
     list.next().to_end()?;
-    Ok(Value::new(DynType::Number(1.0), None))
+    Ok(Value::new(DynType::Bool(true), None))
 }
 
 fn lang_less_than(args: Value) -> Result<Value, Exception> {
@@ -226,8 +283,8 @@ fn lang_less_than(args: Value) -> Result<Value, Exception> {
         match previous.content.partial_cmp(&current.content) {
             Some(cmp) => match cmp {
                 std::cmp::Ordering::Less => {}
-                std::cmp::Ordering::Equal => return Ok(Value::new(DynType::Nil, None)),
-                std::cmp::Ordering::Greater => return Ok(Value::new(DynType::Nil, None)),
+                std::cmp::Ordering::Equal => return Ok(Value::new(DynType::Bool(false), None)),
+                std::cmp::Ordering::Greater => return Ok(Value::new(DynType::Bool(false), None)),
             },
             None => {
                 return Err(Exception {
@@ -247,7 +304,7 @@ fn lang_less_than(args: Value) -> Result<Value, Exception> {
         previous = current;
     }
     list.next().to_end()?;
-    Ok(Value::new(DynType::Number(1.0), None))
+    Ok(Value::new(DynType::Bool(true), None))
 }
 
 fn lang_less_than_or_equals(args: Value) -> Result<Value, Exception> {
@@ -258,7 +315,7 @@ fn lang_less_than_or_equals(args: Value) -> Result<Value, Exception> {
             Some(cmp) => match cmp {
                 std::cmp::Ordering::Less => {}
                 std::cmp::Ordering::Equal => {}
-                std::cmp::Ordering::Greater => return Ok(Value::new(DynType::Nil, None)),
+                std::cmp::Ordering::Greater => return Ok(Value::new(DynType::Bool(false), None)),
             },
             None => {
                 return Err(Exception {
@@ -278,7 +335,7 @@ fn lang_less_than_or_equals(args: Value) -> Result<Value, Exception> {
         previous = current;
     }
     list.next().to_end()?;
-    Ok(Value::new(DynType::Number(1.0), None))
+    Ok(Value::new(DynType::Bool(true), None))
 }
 
 fn lang_cmp(args: Value) -> Result<Value, Exception> {
@@ -289,9 +346,9 @@ fn lang_cmp(args: Value) -> Result<Value, Exception> {
 
     match first.content.partial_cmp(&second.content) {
         Some(cmp) => match cmp {
-            std::cmp::Ordering::Less => Ok(Value::new(DynType::Number(-1.0), None)),
-            std::cmp::Ordering::Equal => Ok(Value::new(DynType::Number(0.0), None)),
-            std::cmp::Ordering::Greater => Ok(Value::new(DynType::Number(1.0), None)),
+            std::cmp::Ordering::Less => Ok(Value::new(DynType::Integer(-1), None)),
+            std::cmp::Ordering::Equal => Ok(Value::new(DynType::Integer(0), None)),
+            std::cmp::Ordering::Greater => Ok(Value::new(DynType::Integer(1), None)),
         },
         None => Err(Exception {
             thrown_object: Value::new(
@@ -307,6 +364,10 @@ fn lang_cmp(args: Value) -> Result<Value, Exception> {
     }
 }
 
+fn lang_list(args: Value) -> Result<Value, Exception> {
+    Ok(args)
+}
+
 fn lang_pair(args: Value) -> Result<Value, Exception> {
     let mut list = List::new(args);
     let left = list.next().to_middle()?;
@@ -350,12 +411,15 @@ fn lang_number(args: Value) -> Result<Value, Exception> {
     list.next().to_end()?;
 
     let number = match &*parameter.content {
-        DynType::Nil => Value::new(DynType::Number(0.0), None),
-        DynType::Number(_) => parameter.clone(),
+        DynType::Nil => Value::new(DynType::Integer(0), None),
+        DynType::Integer(_)
+        | DynType::BigInteger(_)
+        | DynType::Rational(_)
+        | DynType::Number(_) => parameter.clone(),
         DynType::Str(s) => Value::new(
-            DynType::Number(match s.parse::<f64>() {
-                Ok(num) => num,
-                Err(_) => {
+            match number::parse(s) {
+                Some(num) => num,
+                None => {
                     return Err(Exception {
                         thrown_object: Value::new(
                             DynType::Str(format!("Cannot parse '{}' to int", s)),
@@ -365,7 +429,7 @@ fn lang_number(args: Value) -> Result<Value, Exception> {
                         previous_exception: None,
                     })
                 }
-            }),
+            },
             None,
         ),
         other => {
@@ -391,13 +455,25 @@ fn lang_str(args: Value) -> Result<Value, Exception> {
     Ok(Value::new(DynType::Str(format!("{}", item.content)), None))
 }
 
+// strings are quoted and escaped, so the result could be read back
+fn lang_repr(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let item = list.next().to_middle()?;
+    list.next().to_end()?;
+
+    Ok(Value::new(
+        DynType::Str(format!("{:#}", item.content)),
+        None,
+    ))
+}
+
 fn lang_split(args: Value) -> Result<Value, Exception> {
     let mut list = List::new(args);
     let text = list.next().to_middle()?.content.to_string();
 
     let mut splitted: Vec<String> = match list.next() {
         ListItem::Middle(s) => text
-            .split(&(&*s.content).to_string())
+            .split(&(*s.content).to_string())
             .map(str::to_string)
             .collect(),
 
@@ -405,7 +481,7 @@ fn lang_split(args: Value) -> Result<Value, Exception> {
 
         ListItem::Last(_) => {
             return Err(Exception {
-                thrown_object: Value::new(DynType::Str(format!("Syntax Error")), None),
+                thrown_object: Value::new(DynType::Str("Syntax Error".to_string()), None),
                 traceback: vec![],
                 previous_exception: None,
             })
@@ -428,118 +504,400 @@ fn lang_split(args: Value) -> Result<Value, Exception> {
     Ok(pair)
 }
 
-pub fn all_base_functions() -> HashMap<String, Value> {
+fn lang_symbol(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let name = list.next().to_middle()?;
+    list.next().to_end()?;
+
+    Ok(Value::new(
+        DynType::Symbol(Symbol::new(&name.content.to_string())),
+        None,
+    ))
+}
+
+fn lang_gensym(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let prefix = match list.next() {
+        ListItem::Middle(prefix) => prefix.content.to_string(),
+        _ => String::from("g"),
+    };
+    list.next().to_end()?;
+
+    Ok(Value::new(DynType::Symbol(gensym(&prefix)), None))
+}
+
+// (get map key) returns nil for a missing key, (get map key default) returns the default
+fn lang_get(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let map = list.next().to_middle()?;
+    let key = MapKey::from_value(&list.next().to_middle()?)?;
+    let default = match list.next() {
+        ListItem::Middle(default) => {
+            list.next().to_end()?;
+            default
+        }
+        end => {
+            end.to_end()?;
+            Value::new(DynType::Nil, None)
+        }
+    };
+
+    Ok(map.content.to_map()?.get(&key).cloned().unwrap_or(default))
+}
+
+fn lang_assoc(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let mut map = list.next().to_middle()?.content.to_map()?.clone();
+    while let ListItem::Middle(key) = list.next() {
+        let value = list.next().to_middle()?;
+        map = map.assoc(MapKey::from_value(&key)?, value);
+    }
+    list.next().to_end()?;
+
+    Ok(Value::new(DynType::Map(map), None))
+}
+
+fn lang_dissoc(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let mut map = list.next().to_middle()?.content.to_map()?.clone();
+    while let ListItem::Middle(key) = list.next() {
+        map = map.dissoc(&MapKey::from_value(&key)?);
+    }
+    list.next().to_end()?;
+
+    Ok(Value::new(DynType::Map(map), None))
+}
+
+fn lang_keys(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let map = list.next().to_middle()?;
+    list.next().to_end()?;
+
+    let keys = map
+        .content
+        .to_map()?
+        .entries()
+        .map(|(key, _)| key.to_value());
+    Ok(list_of(keys.collect(), Value::new(DynType::Nil, None)))
+}
+
+fn lang_vals(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let map = list.next().to_middle()?;
+    list.next().to_end()?;
+
+    let values = map
+        .content
+        .to_map()?
+        .entries()
+        .map(|(_, value)| value.clone());
+    Ok(list_of(values.collect(), Value::new(DynType::Nil, None)))
+}
+
+fn lang_contains(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let map = list.next().to_middle()?;
+    let key = MapKey::from_value(&list.next().to_middle()?)?;
+    list.next().to_end()?;
+
+    Ok(Value::new(
+        DynType::Bool(map.content.to_map()?.contains(&key)),
+        None,
+    ))
+}
+
+fn index(value: &Value) -> Result<usize, Exception> {
+    let number = value.content.to_integer()?;
+    if number < 0 {
+        return Err(Exception::from(format!(
+            "index must not be negative, given {}",
+            number
+        )));
+    }
+    Ok(number as usize)
+}
+
+fn out_of_range(index: usize, len: usize) -> Exception {
+    Exception::from(format!(
+        "index {} is out of range for the vector of {} items",
+        index, len
+    ))
+}
+
+fn lang_nth(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let vector = list.next().to_middle()?.content.to_vector()?;
+    let index = index(&list.next().to_middle()?)?;
+    list.next().to_end()?;
+
+    vector
+        .get(index)
+        .cloned()
+        .ok_or_else(|| out_of_range(index, vector.len()))
+}
+
+// length of a vector, list, map or string
+fn lang_len(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let value = list.next().to_middle()?;
+    list.next().to_end()?;
+
+    let len = match &*value.content {
+        DynType::Vector(vector) => vector.len(),
+        DynType::Map(map) => map.len(),
+        DynType::Str(string) => string.chars().count(),
+        DynType::Nil | DynType::Pair(_) => {
+            let mut items = List::new(value.clone());
+            let mut len = 0;
+            while let ListItem::Middle(_) = items.next() {
+                len += 1;
+            }
+            items.next().to_end()?;
+            len
+        }
+        other => {
+            return Err(Exception::from(format!(
+                "Expected Vector, List, Map or Str, given, {}",
+                other
+            )))
+        }
+    };
+    Ok(Value::new(DynType::Integer(len as i64), None))
+}
+
+fn lang_push(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let mut vector = list.next().to_middle()?.content.to_vector()?.to_vec();
+    while let ListItem::Middle(item) = list.next() {
+        vector.push(item);
+    }
+    list.next().to_end()?;
+
+    Ok(Value::new(DynType::Vector(Rc::new(vector)), None))
+}
+
+// (slice vector start) or (slice vector start end), the end is not included
+fn lang_slice(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let vector = list.next().to_middle()?.content.to_vector()?;
+    let start = index(&list.next().to_middle()?)?;
+    let end = match list.next() {
+        ListItem::Middle(end) => {
+            list.next().to_end()?;
+            index(&end)?
+        }
+        end => {
+            end.to_end()?;
+            vector.len()
+        }
+    };
+
+    if end > vector.len() {
+        return Err(out_of_range(end, vector.len()));
+    }
+    if start > end {
+        return Err(Exception::from(format!(
+            "start {} of the slice is greater than its end {}",
+            start, end
+        )));
+    }
+    Ok(Value::new(
+        DynType::Vector(Rc::new(vector[start..end].to_vec())),
+        None,
+    ))
+}
+
+fn lang_list_to_vector(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let mut items = List::new(list.next().to_middle()?);
+    list.next().to_end()?;
+
+    let mut vector = vec![];
+    while let ListItem::Middle(item) = items.next() {
+        vector.push(item);
+    }
+    items.next().to_end()?;
+    Ok(Value::new(DynType::Vector(Rc::new(vector)), None))
+}
+
+fn lang_vector_to_list(args: Value) -> Result<Value, Exception> {
+    let mut list = List::new(args);
+    let vector = list.next().to_middle()?.content.to_vector()?;
+    list.next().to_end()?;
+
+    Ok(list_of(vector.to_vec(), Value::new(DynType::Nil, None)))
+}
+
+// a built-in function with the count of arguments it takes, the linter checks the calls by it
+pub struct BaseFunction {
+    pub value: Value,
+    pub arity: Arity,
+}
+
+fn base_function(function: fn(Value) -> Result<Value, Exception>, arity: Arity) -> BaseFunction {
+    BaseFunction {
+        value: Value::new(DynType::Closure(Rc::new(function)), None),
+        arity,
+    }
+}
+
+pub fn all_base_functions() -> HashMap<String, BaseFunction> {
     let mut functions = HashMap::new();
 
     functions.insert(
         "new".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_new(args))), None),
+        base_function(lang_new, Arity::at_least(1)),
     );
 
     functions.insert(
         "apply".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_apply(args))), None),
+        base_function(lang_apply, Arity::exact(2)),
     );
 
     functions.insert(
         "input".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_input(args))), None),
+        base_function(lang_input, Arity::exact(0)),
     );
+    functions.insert("print".to_string(), base_function(lang_print, Arity::any()));
     functions.insert(
-        "print".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_print(args))), None),
+        "println".to_string(),
+        base_function(lang_println, Arity::any()),
     );
+    functions.insert("+".to_string(), base_function(lang_num_add, Arity::any()));
     functions.insert(
-        "println".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_println(args))), None),
+        "-".to_string(),
+        base_function(lang_num_sub, Arity::at_least(1)),
     );
+    functions.insert("*".to_string(), base_function(lang_num_mul, Arity::any()));
     functions.insert(
-        "+".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_num_add(args))), None),
+        "/".to_string(),
+        base_function(lang_num_div, Arity::at_least(1)),
     );
     functions.insert(
-        "-".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_num_sub(args))), None),
+        "%".to_string(),
+        base_function(lang_num_mod, Arity::exact(2)),
     );
     functions.insert(
-        "*".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_num_mul(args))), None),
+        "div".to_string(),
+        base_function(lang_int_div, Arity::exact(2)),
     );
     functions.insert(
-        "/".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_num_div(args))), None),
+        "floor".to_string(),
+        base_function(lang_floor, Arity::exact(1)),
     );
     functions.insert(
-        "%".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_num_mod(args))), None),
+        "ceil".to_string(),
+        base_function(lang_ceil, Arity::exact(1)),
+    );
+    functions.insert(
+        "round".to_string(),
+        base_function(lang_round, Arity::exact(1)),
     );
     functions.insert(
         "=".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_equals(args))), None),
+        base_function(lang_equals, Arity::at_least(1)),
     );
     functions.insert(
         "!=".to_string(),
-        Value::new(
-            DynType::Closure(Rc::new(|args| lang_not_equals(args))),
-            None,
-        ),
+        base_function(lang_not_equals, Arity::at_least(1)),
     );
+    functions.insert("not".to_string(), base_function(lang_not, Arity::exact(1)));
     functions.insert(
         ">".to_string(),
-        Value::new(
-            DynType::Closure(Rc::new(|args| lang_greater_than(args))),
-            None,
-        ),
+        base_function(lang_greater_than, Arity::at_least(1)),
     );
     functions.insert(
         ">=".to_string(),
-        Value::new(
-            DynType::Closure(Rc::new(|args| lang_greater_than_or_equals(args))),
-            None,
-        ),
+        base_function(lang_greater_than_or_equals, Arity::at_least(1)),
     );
     functions.insert(
         "<".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_less_than(args))), None),
+        base_function(lang_less_than, Arity::at_least(1)),
     );
     functions.insert(
         "<=".to_string(),
-        Value::new(
-            DynType::Closure(Rc::new(|args| lang_less_than_or_equals(args))),
-            None,
-        ),
-    );
-    functions.insert(
-        "cmp".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_cmp(args))), None),
+        base_function(lang_less_than_or_equals, Arity::at_least(1)),
     );
+    functions.insert("cmp".to_string(), base_function(lang_cmp, Arity::exact(2)));
+    functions.insert("list".to_string(), base_function(lang_list, Arity::any()));
     functions.insert(
         "pair".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_pair(args))), None),
+        base_function(lang_pair, Arity::exact(2)),
     );
     functions.insert(
         "left".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_left(args))), None),
+        base_function(lang_left, Arity::exact(1)),
     );
     functions.insert(
         "right".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_right(args))), None),
+        base_function(lang_right, Arity::exact(1)),
     );
     functions.insert(
         "concat".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_concat(args))), None),
+        base_function(lang_concat, Arity::any()),
     );
     functions.insert(
         "number".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_number(args))), None),
+        base_function(lang_number, Arity::exact(1)),
     );
+    functions.insert("str".to_string(), base_function(lang_str, Arity::exact(1)));
     functions.insert(
-        "str".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_str(args))), None),
+        "repr".to_string(),
+        base_function(lang_repr, Arity::exact(1)),
     );
     functions.insert(
         "split".to_string(),
-        Value::new(DynType::Closure(Rc::new(|args| lang_split(args))), None),
+        base_function(lang_split, Arity::range(1, 2)),
+    );
+    functions.insert(
+        "symbol".to_string(),
+        base_function(lang_symbol, Arity::exact(1)),
+    );
+    functions.insert(
+        "gensym".to_string(),
+        base_function(lang_gensym, Arity::range(0, 1)),
+    );
+    functions.insert(
+        "get".to_string(),
+        base_function(lang_get, Arity::range(2, 3)),
+    );
+    functions.insert(
+        "assoc".to_string(),
+        base_function(lang_assoc, Arity::at_least(1)),
+    );
+    functions.insert(
+        "dissoc".to_string(),
+        base_function(lang_dissoc, Arity::at_least(1)),
+    );
+    functions.insert(
+        "keys".to_string(),
+        base_function(lang_keys, Arity::exact(1)),
+    );
+    functions.insert(
+        "vals".to_string(),
+        base_function(lang_vals, Arity::exact(1)),
+    );
+    functions.insert(
+        "contains?".to_string(),
+        base_function(lang_contains, Arity::exact(2)),
+    );
+    functions.insert("nth".to_string(), base_function(lang_nth, Arity::exact(2)));
+    functions.insert("len".to_string(), base_function(lang_len, Arity::exact(1)));
+    functions.insert(
+        "push".to_string(),
+        base_function(lang_push, Arity::at_least(1)),
+    );
+    functions.insert(
+        "slice".to_string(),
+        base_function(lang_slice, Arity::range(2, 3)),
+    );
+    functions.insert(
+        "list->vector".to_string(),
+        base_function(lang_list_to_vector, Arity::exact(1)),
+    );
+    functions.insert(
+        "vector->list".to_string(),
+        base_function(lang_vector_to_list, Arity::exact(1)),
     );
     functions
 }
@@ -0,0 +1,209 @@
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    lexer::{self, LexError},
+    parser::{self, ParseError},
+    types::{
+        convert::{FromValue, IntoClosure},
+        exception::Exception,
+        symbol::Symbol,
+        value::Value,
+        DynType,
+    },
+};
+
+use super::{
+    bytecode::Prototype,
+    compiler::Compiler,
+    modules::{mark_loading, module_scope, Module, ModuleRegistry, ModuleRegistryRef},
+    scope::ScopeRef,
+    special_forms::{all_special_forms, SpecialForms},
+    vm::eval,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(String),
+    Lex(LexError),
+    Parse(Vec<ParseError>),
+    // variables which are used but not defined, nothing is run then
+    Undefined(Vec<Exception>),
+    Runtime(Exception),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(message) => write!(f, "{}", message),
+            Error::Lex(err) => write!(f, "{}", err),
+            Error::Parse(errors) => {
+                let messages: Vec<_> = errors.iter().map(ParseError::to_string).collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+            Error::Undefined(errors) => {
+                let messages: Vec<_> = errors
+                    .iter()
+                    .map(|err| err.thrown_object.content.to_string())
+                    .collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+            Error::Runtime(exception) => write!(f, "{}", exception.thrown_object.content),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Exception> for Error {
+    fn from(exception: Exception) -> Self {
+        Error::Runtime(exception)
+    }
+}
+
+// Keeps the global scope between evaluations, so the definitions of the previous code
+// are visible for the next one
+pub struct Interpreter {
+    special_forms: Rc<SpecialForms>,
+    registry: ModuleRegistryRef,
+    global_scope: ScopeRef,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let registry = ModuleRegistry::new();
+        Self {
+            special_forms: all_special_forms(),
+            global_scope: module_scope(&registry, None),
+            registry,
+        }
+    }
+
+    // imports of the evaluated code are resolved relative to the file
+    pub fn for_file(path: &Path) -> Result<Self, Error> {
+        let registry = ModuleRegistry::new();
+        mark_loading(&registry, path)?;
+        Ok(Self {
+            special_forms: all_special_forms(),
+            global_scope: module_scope(&registry, Some(PathBuf::from(path))),
+            registry,
+        })
+    }
+
+    pub fn eval_file(&mut self, path: &Path) -> Result<Value, Error> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| Error::Io(format!("Cannot read {}, cause: {}", path.display(), err)))?;
+        self.eval_str_from_file(&text, path)
+    }
+
+    // Runs the text of the file in the current global scope. Imports are resolved relative to the file
+    // and the traceback points to it, like for the interpreter of `for_file`.
+    pub fn eval_str_from_file(&mut self, text: &str, path: &Path) -> Result<Value, Error> {
+        let module = Module {
+            path: Some(Rc::new(PathBuf::from(path))),
+            registry: self.registry.clone(),
+        };
+        let previous = self
+            .global_scope
+            .borrow_mut()
+            .module
+            .replace(Rc::new(module));
+        let result = self.eval_str(text);
+        self.global_scope.borrow_mut().module = previous;
+        result
+    }
+
+    // returns the value of the last expression
+    pub fn eval_str(&mut self, text: &str) -> Result<Value, Error> {
+        let lexemes = lexer::lex(&mut text.chars()).map_err(Error::Lex)?;
+        let values = parser::parse(&mut lexemes.into_iter()).map_err(Error::Parse)?;
+        self.eval_values(&mut values.into_iter())
+    }
+
+    // Compiles the code without running it. Macros defined by the code are still defined,
+    // since they expand the code which goes after them.
+    pub fn compile_str(&mut self, text: &str) -> Result<Vec<Rc<Prototype>>, Error> {
+        let lexemes = lexer::lex(&mut text.chars()).map_err(Error::Lex)?;
+        let values = parser::parse(&mut lexemes.into_iter()).map_err(Error::Parse)?;
+        let mut compiler = Compiler::new(self.special_forms.clone(), self.global_scope.clone());
+        let mut prototypes = vec![];
+        for value in values {
+            prototypes.push(compiler.compile_top_level(value)?);
+        }
+        compiler.finish().map_err(Error::Undefined)?;
+        Ok(prototypes)
+    }
+
+    pub fn eval_values(&mut self, values: &mut dyn Iterator<Item = Value>) -> Result<Value, Error> {
+        let mut last = Value::new(DynType::Nil, None);
+        self.eval_each(values.collect(), |result| last = result)?;
+        Ok(last)
+    }
+
+    // Every value is compiled and run before the next one, a value which uses an undefined variable isn't run.
+    // Results of the top-level expressions are given to the callback.
+    pub fn eval_each(&mut self, values: Vec<Value>, each: impl FnMut(Value)) -> Result<(), Error> {
+        eval(
+            self.special_forms.clone(),
+            self.global_scope.clone(),
+            values,
+            each,
+        )?;
+        Ok(())
+    }
+
+    // native functions are visible for every module
+    pub fn register_function<F>(&mut self, name: &str, function: F)
+    where
+        F: Fn(Value) -> Result<Value, Exception> + 'static,
+    {
+        self.registry.borrow().builtins().borrow_mut().insert(
+            Symbol::new(name),
+            Value::new(DynType::Closure(Rc::new(function)), None),
+        );
+    }
+
+    // arguments and result of the function are converted by FromValue and IntoValue
+    pub fn register_fn<Args, F: IntoClosure<Args>>(&mut self, name: &str, function: F) {
+        self.registry.borrow().builtins().borrow_mut().insert(
+            Symbol::new(name),
+            Value::new(DynType::Closure(function.into_closure()), None),
+        );
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.global_scope
+            .borrow_mut()
+            .insert(Symbol::new(name), value);
+    }
+
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.global_scope.borrow().variable(Symbol::new(name)).ok()
+    }
+
+    pub fn global_as<T: FromValue>(&self, name: &str) -> Result<T, Error> {
+        let value = self.global_scope.borrow().variable(Symbol::new(name))?;
+        Ok(T::from_value(&value)?)
+    }
+
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        let mut globals: Vec<_> = self
+            .global_scope
+            .borrow()
+            .variables()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        globals.sort_by(|(name1, _), (name2, _)| name1.cmp(name2));
+        globals
+    }
+}
@@ -0,0 +1,931 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    path::Path,
+    rc::Rc,
+};
+
+use crate::{
+    lexer, parser,
+    types::{
+        list::{List, ListItem},
+        symbol::Symbol,
+        value::Value,
+        DynType,
+    },
+    Error,
+};
+
+use super::{
+    arity::Arity,
+    functions::all_base_functions,
+    scope::ScopeState,
+    special_forms::{all_special_forms, SpecialForms},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    // the code fails to compile or to run
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "Warning"),
+            Severity::Error => write!(f, "Error"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub start: (u32, u32),
+    pub end: (u32, u32),
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}-{}", self.message, self.start.0, self.start.1)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Variable,
+    Function(Arity),
+    Macro,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kind::Variable => write!(f, "variable"),
+            Kind::Function(_) => write!(f, "function"),
+            Kind::Macro => write!(f, "macro"),
+        }
+    }
+}
+
+struct Binding {
+    kind: Kind,
+    position: Option<(u32, u32)>,
+    used: bool,
+}
+
+// Where the name is found, the innermost binding wins like in the compiler
+enum Found {
+    Block(usize),
+    Global,
+    Builtin(Option<Arity>),
+    // `prefix/name` of an imported module
+    Imported,
+}
+
+// Checks the parsed code without running it. The code of macros is not expanded, so the arguments
+// of a macro call are only marked as used.
+struct Linter {
+    special_forms: Rc<SpecialForms>,
+    builtins: HashMap<Symbol, Option<Arity>>,
+    // top-level definitions are collected first, since functions could use them above the definition
+    globals: HashMap<Symbol, Binding>,
+    // globals which the top-level code has passed, others are undefined outside of functions
+    defined: HashSet<Symbol>,
+    // count of functions around the expression, their bodies could use the globals defined below
+    functions: usize,
+    // globals which the body of a macro uses above their definitions, a macro runs when it
+    // is called, so they must be defined before the call
+    macro_globals: HashMap<Symbol, Vec<Symbol>>,
+    // the body of the macro which is checked now
+    macro_body: Option<Vec<Symbol>>,
+    prefixes: Vec<String>,
+    // blocks of functions and `do` forms, the innermost is the last
+    blocks: Vec<HashMap<Symbol, Binding>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+// params are a list of symbols with an optional rest symbol after the dot
+fn params_arity(params: &Value) -> Arity {
+    let mut list = List::new(params.clone());
+    let mut count = 0;
+    while let ListItem::Middle(_) = list.next() {
+        count += 1;
+    }
+    match list.next() {
+        ListItem::Last(_) => Arity::at_least(count),
+        _ => Arity::exact(count),
+    }
+}
+
+// items of the list and the value after the dot
+fn items(value: &Value) -> (Vec<Value>, Option<Value>) {
+    let mut list = List::new(value.clone());
+    let mut items = vec![];
+    while let ListItem::Middle(item) = list.next() {
+        items.push(item);
+    }
+    match list.next() {
+        ListItem::Last(rest) => (items, Some(rest)),
+        _ => (items, None),
+    }
+}
+
+fn symbol_of(value: &Value) -> Option<Symbol> {
+    match &*value.content {
+        DynType::Symbol(symbol) => Some(*symbol),
+        _ => None,
+    }
+}
+
+// the name and the kind defined by the form, `(lambda ...)` bound by `let` is a function too
+fn definition(form: &Value) -> Option<(Value, Kind)> {
+    let (items, _) = items(form);
+    let head = items.first().and_then(symbol_of)?;
+    match &*head.name() {
+        "let" => {
+            let kind = match items.get(2).map(items_of_lambda) {
+                Some(Some(params)) => Kind::Function(params_arity(&params)),
+                _ => Kind::Variable,
+            };
+            Some((items.get(1)?.clone(), kind))
+        }
+        "def" | "defmacro" | "defsyntax" => {
+            let pair = items.get(1)?.content.to_pair().ok()?;
+            let kind = if &*head.name() == "def" {
+                Kind::Function(params_arity(&pair.right))
+            } else {
+                Kind::Macro
+            };
+            Some((pair.left.clone(), kind))
+        }
+        "struct" => Some((items.get(1)?.clone(), Kind::Variable)),
+        _ => None,
+    }
+}
+
+fn items_of_lambda(value: &Value) -> Option<Value> {
+    let (items, _) = items(value);
+    match items.first().and_then(symbol_of) {
+        Some(head) if &*head.name() == "lambda" => items.get(1).cloned(),
+        _ => None,
+    }
+}
+
+impl Linter {
+    fn new() -> Self {
+        let mut builtins: HashMap<_, _> = all_base_functions()
+            .into_iter()
+            .map(|(name, function)| (Symbol::new(&name), Some(function.arity)))
+            .collect();
+        builtins.insert(Symbol::new("nil"), None);
+        Self {
+            special_forms: all_special_forms(),
+            builtins,
+            globals: HashMap::new(),
+            defined: HashSet::new(),
+            functions: 0,
+            macro_globals: HashMap::new(),
+            macro_body: None,
+            prefixes: vec![],
+            blocks: vec![],
+            diagnostics: vec![],
+        }
+    }
+
+    fn report(&mut self, severity: Severity, message: String, value: &Value) {
+        let start = match value.position {
+            Some(position) => position,
+            None => return,
+        };
+        // a symbol is marked whole, a list by its bracket
+        let end = match &*value.content {
+            DynType::Symbol(symbol) => {
+                (start.0, start.1 + symbol.name().chars().count() as u32 - 1)
+            }
+            _ => start,
+        };
+        self.diagnostics.push(Diagnostic {
+            severity,
+            message,
+            start,
+            end,
+        });
+    }
+
+    fn error(&mut self, message: String, value: &Value) {
+        self.report(Severity::Error, message, value)
+    }
+
+    fn warning(&mut self, message: String, value: &Value) {
+        self.report(Severity::Warning, message, value)
+    }
+
+    fn find(&self, name: Symbol) -> Option<Found> {
+        if let Some(depth) = self
+            .blocks
+            .iter()
+            .rposition(|block| block.contains_key(&name))
+        {
+            return Some(Found::Block(depth));
+        }
+        if self.globals.contains_key(&name) && (self.functions > 0 || self.defined.contains(&name))
+        {
+            return Some(Found::Global);
+        }
+        if let Some(arity) = self.builtins.get(&name) {
+            return Some(Found::Builtin(*arity));
+        }
+        let imported = self.prefixes.iter().any(|prefix| {
+            name.name()
+                .strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+        });
+        if imported {
+            return Some(Found::Imported);
+        }
+        None
+    }
+
+    // marks the binding as used and returns its kind
+    fn use_name(&mut self, name: Symbol) -> Option<Found> {
+        let found = self.find(name)?;
+        let binding = match found {
+            Found::Block(depth) => self.blocks[depth].get_mut(&name),
+            Found::Global => self.globals.get_mut(&name),
+            _ => None,
+        };
+        if let Some(binding) = binding {
+            binding.used = true;
+        }
+        Some(found)
+    }
+
+    // reports the undefined variable
+    fn use_variable(&mut self, name: Symbol, value: &Value) -> Option<Found> {
+        let found = self.use_name(name);
+        if let (Some(Found::Global), Some(body)) = (&found, &mut self.macro_body) {
+            if !self.defined.contains(&name) && !body.contains(&name) {
+                body.push(name);
+            }
+        }
+        if found.is_none() {
+            let message = if self.globals.contains_key(&name) {
+                format!("variable {} is used before its definition", name)
+            } else {
+                format!("variable {} is undefined", name)
+            };
+            self.error(message, value);
+        }
+        found
+    }
+
+    fn kind(&self, name: Symbol, found: &Found) -> Option<Kind> {
+        match found {
+            Found::Block(depth) => self.blocks[*depth].get(&name).map(|binding| binding.kind),
+            Found::Global => self.globals.get(&name).map(|binding| binding.kind),
+            Found::Builtin(Some(arity)) => Some(Kind::Function(*arity)),
+            Found::Builtin(None) | Found::Imported => None,
+        }
+    }
+
+    // declares the local variable, top-level ones are collected before and only become defined here
+    fn define(&mut self, name: &Value, kind: Kind) {
+        let symbol = match symbol_of(name) {
+            Some(symbol) => symbol,
+            None => return self.error(format!("Expected a symbol, given {}", name.content), name),
+        };
+        if self.blocks.is_empty() {
+            self.defined.insert(symbol);
+            return;
+        }
+        if self.blocks.last().unwrap().contains_key(&symbol) {
+            return self.error(format!("variable {} already exists", symbol), name);
+        }
+        match self.find(symbol) {
+            Some(Found::Block(_)) => {
+                self.warning(format!("{} {} shadows the outer one", kind, symbol), name)
+            }
+            Some(Found::Global) => {
+                self.warning(format!("{} {} shadows the global one", kind, symbol), name)
+            }
+            Some(Found::Builtin(_)) => self.warning(
+                format!("{} {} shadows the built-in one", kind, symbol),
+                name,
+            ),
+            _ => {}
+        }
+        self.blocks.last_mut().unwrap().insert(
+            symbol,
+            Binding {
+                kind,
+                position: name.position,
+                used: false,
+            },
+        );
+    }
+
+    fn begin_block(&mut self) {
+        self.blocks.push(HashMap::new());
+    }
+
+    // names which begin with `_` are unused on purpose
+    fn end_block(&mut self) {
+        let block = self.blocks.pop().unwrap_or_default();
+        let mut unused: Vec<_> = block
+            .into_iter()
+            .filter(|(name, binding)| !binding.used && !name.name().starts_with('_'))
+            .collect();
+        unused.sort_by_key(|(_, binding)| binding.position);
+        for (name, binding) in unused {
+            let value = Value::new(DynType::Symbol(name), binding.position);
+            self.warning(format!("{} {} is never used", binding.kind, name), &value);
+        }
+    }
+
+    fn collect_globals(&mut self, values: &[Value]) {
+        for value in values {
+            if let Some((name, kind)) = definition(value) {
+                let symbol = match symbol_of(&name) {
+                    Some(symbol) => symbol,
+                    None => continue,
+                };
+                if self.globals.contains_key(&symbol) {
+                    self.error(format!("variable {} already exists", symbol), &name);
+                    continue;
+                }
+                let binding = Binding {
+                    kind,
+                    position: name.position,
+                    used: false,
+                };
+                self.globals.insert(symbol, binding);
+            }
+            let (items, _) = items(value);
+            if items
+                .first()
+                .and_then(symbol_of)
+                .map(Symbol::name)
+                .as_deref()
+                == Some("import")
+            {
+                self.collect_import(&items[1..]);
+            }
+        }
+    }
+
+    // `(import "path" (names))` defines the names, other imports define `prefix/name`
+    fn collect_import(&mut self, args: &[Value]) {
+        let path = match args.first().map(|path| &*path.content) {
+            Some(DynType::Str(path)) => path.clone(),
+            _ => return,
+        };
+        match args.get(1) {
+            Some(names) if matches!(&*names.content, DynType::Pair(_)) => {
+                for name in items(names).0 {
+                    if let Some(symbol) = symbol_of(&name) {
+                        let binding = Binding {
+                            kind: Kind::Variable,
+                            position: name.position,
+                            used: false,
+                        };
+                        self.globals.insert(symbol, binding);
+                    }
+                }
+            }
+            Some(prefix) => {
+                if let Some(symbol) = symbol_of(prefix) {
+                    self.prefixes.push(symbol.name().to_string());
+                }
+            }
+            None => {
+                let stem = Path::new(&path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                self.prefixes.push(stem);
+            }
+        }
+    }
+
+    fn expression(&mut self, value: &Value, scope_state: ScopeState) {
+        match &*value.content {
+            DynType::Symbol(symbol) => {
+                self.use_variable(*symbol, value);
+            }
+            DynType::Pair(_) => self.call(value, scope_state),
+            DynType::Quasiquoted(template) => self.template(template, 1),
+            DynType::Unquoted(_) | DynType::UnquoteSpliced(_) => self.error(
+                "Unquote is allowed only inside quasiquote".to_string(),
+                value,
+            ),
+            DynType::Map(map) => {
+                for (_, item) in map.entries() {
+                    self.expression(item, ScopeState::Expression);
+                }
+            }
+            DynType::Vector(vector) => {
+                for item in vector.iter() {
+                    self.expression(item, ScopeState::Expression);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // the unquoted parts of the template are calculated
+    fn template(&mut self, template: &Value, depth: usize) {
+        match &*template.content {
+            DynType::Unquoted(unquoted) | DynType::UnquoteSpliced(unquoted) if depth == 1 => {
+                self.expression(unquoted, ScopeState::Expression)
+            }
+            DynType::Unquoted(unquoted) | DynType::UnquoteSpliced(unquoted) => {
+                self.template(unquoted, depth - 1)
+            }
+            DynType::Quasiquoted(quasiquoted) => self.template(quasiquoted, depth + 1),
+            DynType::Pair(pair) => {
+                self.template(&pair.left, depth);
+                self.template(&pair.right, depth);
+            }
+            _ => {}
+        }
+    }
+
+    // the code which is not checked, like arguments of macros, still uses the variables
+    fn mark_used(&mut self, value: &Value) {
+        match &*value.content {
+            DynType::Symbol(symbol) => {
+                self.use_name(*symbol);
+            }
+            DynType::Pair(pair) => {
+                self.mark_used(&pair.left);
+                self.mark_used(&pair.right);
+            }
+            DynType::Quoted(inner)
+            | DynType::Quasiquoted(inner)
+            | DynType::Unquoted(inner)
+            | DynType::UnquoteSpliced(inner) => self.mark_used(inner),
+            DynType::Map(map) => {
+                for (_, item) in map.entries() {
+                    self.mark_used(item);
+                }
+            }
+            DynType::Vector(vector) => {
+                for item in vector.iter() {
+                    self.mark_used(item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // the top-level expressions above the call have run when the macro is expanded
+    fn check_macro_globals(&mut self, name: Symbol, head: &Value) {
+        let undefined: Vec<_> = self
+            .macro_globals
+            .get(&name)
+            .into_iter()
+            .flatten()
+            .filter(|global| !self.defined.contains(global))
+            .copied()
+            .collect();
+        for global in undefined {
+            let message = format!(
+                "macro {} uses variable {} before its definition",
+                name, global
+            );
+            self.error(message, head);
+        }
+    }
+
+    fn check_arity(&mut self, name: Symbol, arity: Arity, given: usize, value: &Value) -> bool {
+        if arity.accepts(given) {
+            return true;
+        }
+        self.error(format!("{} takes {}, given {}", name, arity, given), value);
+        false
+    }
+
+    fn call(&mut self, value: &Value, scope_state: ScopeState) {
+        let (items, rest) = items(value);
+        let head = &items[0];
+        let args = &items[1..];
+        if let Some(rest) = rest {
+            self.error(
+                format!(
+                    "Arguments must be a list, found {} after the dot",
+                    rest.content
+                ),
+                value,
+            );
+        }
+
+        if let Some(symbol) = symbol_of(head) {
+            if let Some(special_form) = self.special_forms.clone().get(&symbol) {
+                if scope_state > special_form.possible_scope_state {
+                    self.error(
+                        format!(
+                            "{} special form is allowed for {:?} scope but {:?} scope is given",
+                            special_form.name, special_form.possible_scope_state, scope_state,
+                        ),
+                        head,
+                    );
+                }
+                return self.special_form(symbol, head, args);
+            }
+
+            let found = match self.use_variable(symbol, head) {
+                Some(found) => found,
+                None => return self.mark_used(value),
+            };
+            match self.kind(symbol, &found) {
+                Some(Kind::Macro) => {
+                    self.check_macro_globals(symbol, head);
+                    return self.mark_used(value);
+                }
+                Some(Kind::Function(arity)) => {
+                    self.check_arity(symbol, arity, args.len(), head);
+                }
+                _ => {}
+            }
+        } else {
+            self.expression(head, ScopeState::Expression);
+        }
+        for arg in args {
+            self.expression(arg, ScopeState::Expression);
+        }
+    }
+
+    fn special_form(&mut self, name: Symbol, head: &Value, args: &[Value]) {
+        let arity = self.special_forms[&name].arity;
+        if !self.check_arity(name, arity, args.len(), head) {
+            return args.iter().for_each(|arg| self.mark_used(arg));
+        }
+
+        match &*name.name() {
+            "let" => {
+                self.expression(&args[1], ScopeState::Expression);
+                let kind = match items_of_lambda(&args[1]) {
+                    Some(params) => Kind::Function(params_arity(&params)),
+                    None => Kind::Variable,
+                };
+                self.define(&args[0], kind);
+            }
+            "set" => {
+                self.expression(&args[1], ScopeState::Expression);
+                let symbol = match symbol_of(&args[0]) {
+                    Some(symbol) => symbol,
+                    None => {
+                        let message = format!("Expected a symbol, given {}", args[0].content);
+                        return self.error(message, &args[0]);
+                    }
+                };
+                match self.find(symbol) {
+                    None => self.error(
+                        format!("variable {} is undefined and cannot be set", symbol),
+                        &args[0],
+                    ),
+                    Some(Found::Builtin(_)) => {
+                        self.error(format!("built-in {} cannot be set", symbol), &args[0])
+                    }
+                    Some(found) => {
+                        if let Some(Kind::Macro) = self.kind(symbol, &found) {
+                            return self.error(format!("macro {} cannot be set", symbol), &args[0]);
+                        }
+                        // the new value could take other arguments, so the calls aren't checked
+                        let binding = match found {
+                            Found::Block(depth) => self.blocks[depth].get_mut(&symbol),
+                            Found::Global => self.globals.get_mut(&symbol),
+                            _ => None,
+                        };
+                        if let Some(binding) = binding {
+                            binding.kind = Kind::Variable;
+                        }
+                    }
+                }
+            }
+            "def" => match &*args[0].content {
+                DynType::Pair(signature) => {
+                    let kind = Kind::Function(params_arity(&signature.right));
+                    // the function is declared first, so it can call itself
+                    self.define(&signature.left, kind);
+                    self.function(&signature.right, &args[1]);
+                }
+                _ => self.error(
+                    format!("Expected a list, given {}", args[0].content),
+                    &args[0],
+                ),
+            },
+            // the body of a macro runs during the compilation and sees only the global variables
+            "defmacro" | "defsyntax" => match &*args[0].content {
+                DynType::Pair(signature) => {
+                    let blocks = std::mem::take(&mut self.blocks);
+                    let outer_body = self.macro_body.replace(vec![]);
+                    self.function(&signature.right, &args[1]);
+                    let globals = std::mem::replace(&mut self.macro_body, outer_body);
+                    self.blocks = blocks;
+                    self.define(&signature.left, Kind::Macro);
+                    if let Some(name) = symbol_of(&signature.left) {
+                        self.macro_globals.insert(name, globals.unwrap_or_default());
+                    }
+                }
+                _ => self.error(
+                    format!("Expected a list, given {}", args[0].content),
+                    &args[0],
+                ),
+            },
+            "macroexpand" => self.mark_used(&args[0]),
+            "lambda" => self.function(&args[0], &args[1]),
+            "do" => {
+                self.begin_block();
+                for arg in args {
+                    self.expression(arg, ScopeState::Local);
+                }
+                self.end_block();
+            }
+            "struct" => {
+                let struct_name = symbol_of(&args[0]);
+                let mut fields: Vec<Symbol> = vec![];
+                for field in items(&args[1]).0 {
+                    match symbol_of(&field) {
+                        Some(symbol) if fields.contains(&symbol) => self.error(
+                            format!(
+                                "field {} is duplicated in struct {}",
+                                symbol,
+                                struct_name.map_or("".into(), Symbol::name)
+                            ),
+                            &field,
+                        ),
+                        Some(symbol) => fields.push(symbol),
+                        None => self.error(
+                            format!("Expected a symbol, given {}", field.content),
+                            &field,
+                        ),
+                    }
+                }
+                self.define(&args[0], Kind::Variable);
+            }
+            "::" => {
+                self.expression(&args[0], ScopeState::Expression);
+                if symbol_of(&args[1]).is_none() {
+                    self.error(
+                        format!("Expected a field name, given {}", args[1].content),
+                        &args[1],
+                    );
+                }
+            }
+            "import" => {
+                if !matches!(&*args[0].content, DynType::Str(_)) {
+                    self.error(
+                        format!("Expected module path, given {}", args[0].content),
+                        &args[0],
+                    );
+                }
+                if let Some(names) = args
+                    .get(1)
+                    .filter(|names| matches!(&*names.content, DynType::Pair(_)))
+                {
+                    for name in items(names).0 {
+                        self.define(&name, Kind::Variable);
+                    }
+                }
+            }
+            "try" => {
+                self.expression(&args[0], ScopeState::Expression);
+                for clause in &args[1..] {
+                    self.try_clause(clause);
+                }
+            }
+            // if, and, or, throw
+            _ => {
+                for arg in args {
+                    self.expression(arg, ScopeState::Expression);
+                }
+            }
+        }
+    }
+
+    // (catch symbol expression) or (finally expression)
+    fn try_clause(&mut self, clause: &Value) {
+        let (items, _) = items(clause);
+        match (
+            items
+                .first()
+                .and_then(symbol_of)
+                .map(Symbol::name)
+                .as_deref(),
+            items.len(),
+        ) {
+            (Some("catch"), 3) => {
+                self.begin_block();
+                self.define(&items[1], Kind::Variable);
+                self.expression(&items[2], ScopeState::Expression);
+                self.end_block();
+            }
+            (Some("finally"), 2) => self.expression(&items[1], ScopeState::Expression),
+            _ => self.error(format!("Unexpected try clause {}", clause.content), clause),
+        }
+    }
+
+    fn function(&mut self, params: &Value, body: &Value) {
+        self.begin_block();
+        self.functions += 1;
+        let (params, rest) = items(params);
+        for param in params.iter().chain(rest.iter()) {
+            self.define(param, Kind::Variable);
+        }
+        self.expression(body, ScopeState::Expression);
+        self.functions -= 1;
+        self.end_block();
+    }
+}
+
+// Finds undefined variables, wrong counts of arguments, special forms in wrong places,
+// unused and shadowed variables and duplicated fields of structs.
+// Diagnostics are sorted by their positions.
+pub fn lint(text: &str) -> Result<Vec<Diagnostic>, Error> {
+    let lexemes = lexer::lex(&mut text.chars()).map_err(Error::Lex)?;
+    let values = parser::parse(&mut lexemes.into_iter()).map_err(Error::Parse)?;
+
+    let mut linter = Linter::new();
+    linter.collect_globals(&values);
+    for value in &values {
+        linter.expression(value, ScopeState::Global);
+    }
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.start);
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // severity, message and line of every diagnostic
+    fn check(code: &str) -> Vec<(Severity, String, u32)> {
+        lint(code)
+            .unwrap()
+            .into_iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.message, diagnostic.start.0))
+            .collect()
+    }
+
+    fn error(message: &str, line: u32) -> (Severity, String, u32) {
+        (Severity::Error, message.to_string(), line)
+    }
+
+    fn warning(message: &str, line: u32) -> (Severity, String, u32) {
+        (Severity::Warning, message.to_string(), line)
+    }
+
+    #[test]
+    fn top_level_code_runs_in_order() {
+        let code = "(println (g 1))
+            (let c b)
+            (def (f) (g 2))
+            (let h (lambda () b))
+            (def (g x) x)
+            (let b 1)
+            (println (g 3) (f) (h) c)";
+        assert_eq!(
+            check(code),
+            [
+                error("variable g is used before its definition", 1),
+                error("variable b is used before its definition", 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn macros_run_when_they_are_called() {
+        let code = "(defmacro (m x) (helper x))
+            (println (m 1))
+            (def (helper x) (list (symbol \"+\") x 1))
+            (println (m 2))";
+        assert_eq!(
+            check(code),
+            [error(
+                "macro m uses variable helper before its definition",
+                2
+            )]
+        );
+    }
+
+    #[test]
+    fn undefined_names() {
+        let code = "(println x)
+            (set y 1)
+            (def (f) (do (let z 1) z))
+            (println z (f))";
+        assert_eq!(
+            check(code),
+            [
+                error("variable x is undefined", 1),
+                error("variable y is undefined and cannot be set", 2),
+                error("variable z is undefined", 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_of_arguments() {
+        let code = "(def (f x . _rest) x)
+            (let g (lambda (a _b) a))
+            (f)
+            (f 1 2 3)
+            (g 1)
+            (floor 1 2)
+            (=)
+            (split \"a\" \" \" 1)
+            (if 1 2)
+            (try 1)";
+        assert_eq!(
+            check(code),
+            [
+                error("f takes at least 1 argument, given 0", 3),
+                error("g takes 2 arguments, given 1", 5),
+                error("floor takes 1 argument, given 2", 6),
+                error("= takes at least 1 argument, given 0", 7),
+                error("split takes 1 to 2 arguments, given 3", 8),
+                error("if takes 3 arguments, given 2", 9),
+                error("try takes 2 to 3 arguments, given 1", 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_functions_take_any_arguments() {
+        let code = "(def (f a) a)
+            (f 1 2)
+            (set f (lambda (a b) (+ a b)))
+            (f 1 2)
+            (def (g) (do
+                (let h (lambda () 1))
+                (set h (lambda (x) x))
+                (h 1)))
+            (g)";
+        assert_eq!(check(code), [error("f takes 1 argument, given 2", 2)]);
+    }
+
+    #[test]
+    fn special_forms_in_wrong_places() {
+        let code = "(def (f x) (let _y x))
+            (println (def (g) 1))";
+        assert_eq!(
+            check(code),
+            [
+                error(
+                    "let special form is allowed for Local scope but Expression scope is given",
+                    1
+                ),
+                error(
+                    "def special form is allowed for Local scope but Expression scope is given",
+                    2
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn unused_and_shadowed_bindings() {
+        let code = "(let x 1)
+            (def (f a _b) (do
+                (let x 2)
+                (let unused 3)
+                (let list 4)
+                (let list 5)
+                (+ x list)))
+            (f x 1)";
+        assert_eq!(
+            check(code),
+            [
+                warning("variable a is never used", 2),
+                warning("variable x shadows the global one", 3),
+                warning("variable unused is never used", 4),
+                warning("variable list shadows the built-in one", 5),
+                error("variable list already exists", 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicated_definitions_and_fields() {
+        let code = "(struct Point (x y x))
+            (def (f) 1)
+            (def (f) 2)";
+        assert_eq!(
+            check(code),
+            [
+                error("field x is duplicated in struct Point", 1),
+                error("variable f already exists", 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn builtins_are_not_set() {
+        assert_eq!(check("(set + -)"), [error("built-in + cannot be set", 1)]);
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use crate::types::{
+    dot_pair::DotPair,
+    exception::Exception,
+    list::{List, ListItem},
+    symbol::Symbol,
+    value::Value,
+    DynType,
+};
+
+use super::custom_function::CustomFunction;
+
+thread_local! {
+    static GENSYM_COUNTER: Cell<usize> = const { Cell::new(0) };
+}
+
+// '#' starts a comment, so generated symbols never clash with written ones
+pub fn gensym(prefix: &str) -> Symbol {
+    GENSYM_COUNTER.with(|counter| {
+        counter.set(counter.get() + 1);
+        Symbol::new(&format!("{}#{}", prefix, counter.get()))
+    })
+}
+
+pub struct Macro {
+    function: CustomFunction,
+    hygienic: bool,
+}
+
+impl Macro {
+    pub fn new(function: CustomFunction, hygienic: bool) -> Self {
+        Self { function, hygienic }
+    }
+
+    // Calls the transformer with the unevaluated arguments. Every part of the expansion
+    // which is not taken from the arguments gets the position of the macro call.
+    pub fn expand(&self, args: Value, position: Option<(u32, u32)>) -> Result<Value, Exception> {
+        let expansion = self.function.call(args.clone())?;
+
+        let mut user_nodes = HashSet::new();
+        collect_nodes(&args, &mut user_nodes);
+
+        let mut renames = HashMap::new();
+        if self.hygienic {
+            collect_introduced_binders(&expansion, &user_nodes, &mut renames);
+        }
+        Ok(rebuild(&expansion, &user_nodes, &renames, position))
+    }
+}
+
+fn collect_nodes(value: &Value, nodes: &mut HashSet<*const DynType>) {
+    nodes.insert(Rc::as_ptr(&value.content));
+    match &*value.content {
+        DynType::Pair(pair) => {
+            collect_nodes(&pair.left, nodes);
+            collect_nodes(&pair.right, nodes);
+        }
+        DynType::Quoted(quoted)
+        | DynType::Quasiquoted(quoted)
+        | DynType::Unquoted(quoted)
+        | DynType::UnquoteSpliced(quoted) => collect_nodes(quoted, nodes),
+        _ => {}
+    }
+}
+
+fn is_introduced(value: &Value, user_nodes: &HashSet<*const DynType>) -> bool {
+    !user_nodes.contains(&Rc::as_ptr(&value.content))
+}
+
+fn introduce_binder(
+    value: &Value,
+    user_nodes: &HashSet<*const DynType>,
+    renames: &mut HashMap<Symbol, Symbol>,
+) {
+    if let DynType::Symbol(symbol) = &*value.content {
+        if is_introduced(value, user_nodes) && !renames.contains_key(symbol) {
+            renames.insert(*symbol, gensym(&symbol.name()));
+        }
+    }
+}
+
+fn introduce_binders_of_list(
+    value: &Value,
+    user_nodes: &HashSet<*const DynType>,
+    renames: &mut HashMap<Symbol, Symbol>,
+) {
+    let mut list = List::new(value.clone());
+    while let ListItem::Middle(item) = list.next() {
+        introduce_binder(&item, user_nodes, renames);
+    }
+    if let ListItem::Last(rest) = list.next() {
+        introduce_binder(&rest, user_nodes, renames);
+    }
+}
+
+// Finds variables bound by the forms which the macro has introduced itself.
+// Renaming them prevents capturing the variables of the code given to the macro.
+fn collect_introduced_binders(
+    value: &Value,
+    user_nodes: &HashSet<*const DynType>,
+    renames: &mut HashMap<Symbol, Symbol>,
+) {
+    if !is_introduced(value, user_nodes) {
+        return;
+    }
+    let pair = match &*value.content {
+        DynType::Pair(pair) => pair,
+        _ => return,
+    };
+
+    if is_introduced(&pair.left, user_nodes) {
+        let mut list = List::new(pair.right.clone());
+        match &*pair.left.content {
+            DynType::Symbol(form) if &*form.name() == "let" => {
+                if let ListItem::Middle(name) = list.next() {
+                    introduce_binder(&name, user_nodes, renames);
+                }
+            }
+            DynType::Symbol(form) if &*form.name() == "lambda" => {
+                if let ListItem::Middle(arguments) = list.next() {
+                    introduce_binders_of_list(&arguments, user_nodes, renames);
+                }
+            }
+            DynType::Symbol(form) if matches!(&*form.name(), "def" | "defmacro" | "defsyntax") => {
+                if let ListItem::Middle(signature) = list.next() {
+                    introduce_binders_of_list(&signature, user_nodes, renames);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut items = List::new(value.clone());
+    while let ListItem::Middle(item) = items.next() {
+        collect_introduced_binders(&item, user_nodes, renames);
+    }
+    if let ListItem::Last(rest) = items.next() {
+        collect_introduced_binders(&rest, user_nodes, renames);
+    }
+}
+
+fn rebuild(
+    value: &Value,
+    user_nodes: &HashSet<*const DynType>,
+    renames: &HashMap<Symbol, Symbol>,
+    position: Option<(u32, u32)>,
+) -> Value {
+    if !is_introduced(value, user_nodes) {
+        return value.clone();
+    }
+    match &*value.content {
+        DynType::Pair(pair) => Value::new(
+            DynType::Pair(DotPair {
+                left: rebuild(&pair.left, user_nodes, renames, position),
+                right: rebuild(&pair.right, user_nodes, renames, position),
+            }),
+            position,
+        ),
+        DynType::Symbol(symbol) => match renames.get(symbol) {
+            Some(renamed) => Value::new(DynType::Symbol(*renamed), position),
+            None => Value {
+                content: value.content.clone(),
+                position,
+            },
+        },
+        _ => Value {
+            content: value.content.clone(),
+            position,
+        },
+    }
+}
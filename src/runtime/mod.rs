@@ -1,30 +1,12 @@
-mod calculators;
-mod custom_function;
+pub mod arity;
+pub mod bytecode;
+pub mod compiler;
+pub mod custom_function;
 pub mod functions;
+pub mod interpreter;
+pub mod lint;
+pub mod macros;
+pub mod modules;
 mod scope;
 pub mod special_forms;
-
-use crate::types::exception::Exception;
-use crate::types::value::Value;
-use std::cell::RefCell;
-use std::rc::Rc;
-
-use self::calculators::calculate;
-use self::calculators::create_global_scope;
-use self::scope::ScopeState;
-use self::special_forms::all_special_forms;
-
-pub fn execute(values: &mut dyn Iterator<Item = Value>) -> Result<(), Exception> {
-    let global_scope = Rc::new(RefCell::new(create_global_scope()));
-    let special_forms = all_special_forms();
-    for value in values {
-        calculate(
-            special_forms.clone(),
-            global_scope.clone(),
-            ScopeState::Global,
-            value,
-        )?;
-    }
-
-    Ok(())
-}
+pub mod vm;
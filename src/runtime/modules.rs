@@ -0,0 +1,240 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    lexer, parser,
+    types::{exception::Exception, symbol::Symbol, value::Value, DynType},
+    Error,
+};
+
+use super::{
+    scope::{create_global_scope, Scope, ScopeRef},
+    special_forms::SpecialForms,
+    vm::eval,
+};
+
+pub type ModuleRegistryRef = Rc<RefCell<ModuleRegistry>>;
+
+pub struct Module {
+    pub path: Option<Rc<PathBuf>>,
+    pub registry: ModuleRegistryRef,
+}
+
+enum ModuleState {
+    Loading,
+    Loaded(ScopeRef),
+}
+
+// Every module is executed once, the registry keeps their top-level scopes by the canonical path
+pub struct ModuleRegistry {
+    builtins: ScopeRef,
+    modules: HashMap<PathBuf, ModuleState>,
+}
+
+impl ModuleRegistry {
+    pub fn new() -> ModuleRegistryRef {
+        Rc::new(RefCell::new(ModuleRegistry {
+            builtins: Rc::new(RefCell::new(create_global_scope())),
+            modules: HashMap::new(),
+        }))
+    }
+
+    pub fn builtins(&self) -> ScopeRef {
+        self.builtins.clone()
+    }
+}
+
+fn import_exception(message: String) -> Exception {
+    Exception::new(Value::new(DynType::Str(message), None))
+}
+
+// top-level scope of a module, builtins are in the outer one
+pub fn module_scope(registry: &ModuleRegistryRef, path: Option<PathBuf>) -> ScopeRef {
+    let mut scope = Scope::new(Some(registry.borrow().builtins.clone()));
+    scope.module = Some(Rc::new(Module {
+        path: path.map(Rc::new),
+        registry: registry.clone(),
+    }));
+    Rc::new(RefCell::new(scope))
+}
+
+// the path is relative to the directory of the importing file, `.yal` extension could be omitted
+pub fn resolve(importer: Option<&Path>, name: &str) -> Result<PathBuf, Exception> {
+    let directory = importer
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let path = directory.join(name);
+    if path.is_file() {
+        return Ok(path);
+    }
+    let with_extension = directory.join(format!("{}.yal", name));
+    if with_extension.is_file() {
+        return Ok(with_extension);
+    }
+    Err(import_exception(format!(
+        "module {} is not found",
+        path.display()
+    )))
+}
+
+fn canonical(path: &Path) -> Result<PathBuf, Exception> {
+    path.canonicalize().map_err(|err| {
+        import_exception(format!(
+            "cannot open module {}, cause: {}",
+            path.display(),
+            err
+        ))
+    })
+}
+
+pub fn mark_loading(registry: &ModuleRegistryRef, path: &Path) -> Result<(), Exception> {
+    let canonical = canonical(path)?;
+    registry
+        .borrow_mut()
+        .modules
+        .insert(canonical, ModuleState::Loading);
+    Ok(())
+}
+
+pub fn load(
+    special_forms: Rc<SpecialForms>,
+    registry: &ModuleRegistryRef,
+    path: &Path,
+) -> Result<ScopeRef, Exception> {
+    let canonical = canonical(path)?;
+    match registry.borrow().modules.get(&canonical) {
+        Some(ModuleState::Loaded(scope)) => return Ok(scope.clone()),
+        Some(ModuleState::Loading) => {
+            return Err(import_exception(format!(
+                "circular import of module {}",
+                path.display()
+            )))
+        }
+        None => {}
+    }
+
+    registry
+        .borrow_mut()
+        .modules
+        .insert(canonical.clone(), ModuleState::Loading);
+    match execute_file(special_forms, registry, path) {
+        Ok(scope) => {
+            registry
+                .borrow_mut()
+                .modules
+                .insert(canonical, ModuleState::Loaded(scope.clone()));
+            Ok(scope)
+        }
+        Err(err) => {
+            registry.borrow_mut().modules.remove(&canonical);
+            Err(err)
+        }
+    }
+}
+
+fn execute_file(
+    special_forms: Rc<SpecialForms>,
+    registry: &ModuleRegistryRef,
+    path: &Path,
+) -> Result<ScopeRef, Exception> {
+    let text = fs::read_to_string(path).map_err(|err| {
+        import_exception(format!(
+            "cannot read module {}, cause: {}",
+            path.display(),
+            err
+        ))
+    })?;
+    let lexemes = lexer::lex(&mut text.chars()).map_err(|err| {
+        import_exception(format!("cannot import module {}: {}", path.display(), err))
+    })?;
+    let values = parser::parse(&mut lexemes.into_iter()).map_err(|errors| {
+        let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        import_exception(format!(
+            "cannot import module {}: {}",
+            path.display(),
+            messages.join(", ")
+        ))
+    })?;
+
+    let scope = module_scope(registry, Some(path.to_path_buf()));
+    match eval(special_forms, scope.clone(), values, |_| {}) {
+        Ok(()) => Ok(scope),
+        // every undefined variable goes to the causes, so their positions are printed
+        Err(Error::Undefined(errors)) => {
+            let message = format!(
+                "cannot import module {}: undefined variables",
+                path.display()
+            );
+            Err(errors
+                .into_iter()
+                .fold(import_exception(message), Exception::caused_by))
+        }
+        Err(Error::Runtime(err)) => Err(err),
+        Err(err) => Err(import_exception(format!(
+            "cannot import module {}: {}",
+            path.display(),
+            err
+        ))),
+    }
+}
+
+pub enum ImportNames {
+    // (import "file" (name1 name2)) defines the listed names as is
+    Listed(Vec<Symbol>),
+    // (import "file" prefix) or (import "file") defines every top-level name as prefix/name
+    Prefixed(Option<Symbol>),
+}
+
+pub fn import(
+    special_forms: Rc<SpecialForms>,
+    scope: &ScopeRef,
+    name: &str,
+    names: &ImportNames,
+) -> Result<(), Exception> {
+    let module = scope
+        .borrow()
+        .module()
+        .ok_or_else(|| import_exception("import is allowed only inside a module".to_string()))?;
+    let path = resolve(module.path.as_deref().map(|path| path.as_path()), name)?;
+    let module_scope = load(special_forms, &module.registry, &path)?;
+    let module_scope = module_scope.borrow();
+
+    match names {
+        ImportNames::Listed(names) => {
+            for name in names {
+                let value = match module_scope.find(*name).map(|slot| module_scope.get(slot)) {
+                    Some(Ok(value)) => value,
+                    _ => {
+                        return Err(import_exception(format!(
+                            "{} is not defined in module {}",
+                            name,
+                            path.display()
+                        )))
+                    }
+                };
+                scope.borrow_mut().define_variable(*name, value)?;
+            }
+        }
+        ImportNames::Prefixed(prefix) => {
+            let prefix = match prefix {
+                Some(prefix) => prefix.to_string(),
+                None => path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            };
+            for (name, value) in module_scope.variables() {
+                scope
+                    .borrow_mut()
+                    .define_variable(Symbol::new(&format!("{}/{}", prefix, name)), value.clone())?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,54 +1,147 @@
 use std::{cell::RefCell, cmp::Ordering, collections::HashMap, rc::Rc};
 
-use crate::types::{exception::Exception, value::Value, DynType};
+use crate::types::{
+    exception::{Exception, Frame},
+    symbol::Symbol,
+    value::Value,
+    DynType,
+};
 
+use super::{functions::all_base_functions, modules::Module};
+
+struct Slot {
+    name: Symbol,
+    // the slot exists before its variable is defined, when the variable is used above its definition
+    value: Option<Value>,
+}
+
+// Variables of a module or the built-in ones. The compiled code takes them by the slot index,
+// names are needed only to find the slot.
 pub struct Scope {
-    pub variables: HashMap<String, Value>,
+    names: HashMap<Symbol, usize>,
+    slots: Vec<Slot>,
     pub outer_scope: Option<ScopeRef>,
+    pub module: Option<Rc<Module>>,
 }
 
 pub type ScopeRef = Rc<RefCell<Scope>>;
 
+pub fn create_global_scope() -> Scope {
+    let mut global_scope = Scope::new(None);
+    for (name, function) in all_base_functions() {
+        global_scope.insert(Symbol::new(&name), function.value);
+    }
+    global_scope.insert(Symbol::new("nil"), Value::new(DynType::Nil, None));
+    global_scope
+}
+
+fn undefined_error(name: Symbol) -> Exception {
+    Exception::from(format!("variable {} is undefined", name))
+}
+
 impl Scope {
     pub fn new(outer_scope: Option<ScopeRef>) -> Scope {
         Scope {
-            variables: HashMap::new(),
+            names: HashMap::new(),
+            slots: vec![],
             outer_scope,
+            module: None,
+        }
+    }
+
+    // module of the top-level scope
+    pub fn module(&self) -> Option<Rc<Module>> {
+        match (&self.module, &self.outer_scope) {
+            (Some(module), _) => Some(module.clone()),
+            (None, Some(outer_scope)) => outer_scope.borrow().module(),
+            (None, None) => None,
+        }
+    }
+
+    pub fn frame(&self, position: Option<(u32, u32)>) -> Frame {
+        Frame {
+            file: self.module().and_then(|module| module.path.clone()),
+            position,
+        }
+    }
+
+    pub fn find(&self, name: Symbol) -> Option<usize> {
+        self.names.get(&name).copied()
+    }
+
+    // finds the slot of the name or adds an empty one
+    pub fn slot(&mut self, name: Symbol) -> usize {
+        if let Some(slot) = self.find(name) {
+            return slot;
+        }
+        self.slots.push(Slot { name, value: None });
+        self.names.insert(name, self.slots.len() - 1);
+        self.slots.len() - 1
+    }
+
+    pub fn is_defined(&self, slot: usize) -> bool {
+        self.slots[slot].value.is_some()
+    }
+
+    pub fn get(&self, slot: usize) -> Result<Value, Exception> {
+        let slot = &self.slots[slot];
+        slot.value.clone().ok_or_else(|| undefined_error(slot.name))
+    }
+
+    pub fn define(&mut self, slot: usize, value: Value) -> Result<(), Exception> {
+        let slot = &mut self.slots[slot];
+        if slot.value.is_some() {
+            return Err(Exception::from(format!(
+                "variable {} already exists",
+                slot.name
+            )));
         }
+        slot.value = Some(value);
+        Ok(())
     }
 
-    pub fn variable(&self, name: &String) -> Result<Value, Exception> {
-        if let Some(value) = self.variables.get(name) {
-            Ok(value.clone())
-        } else if let Some(outer_scope) = &self.outer_scope {
-            outer_scope.borrow_mut().variable(name)
-        } else {
-            Err(Exception {
-                thrown_object: Value::new(
-                    DynType::Str(format!("variable {} is undefined", name)),
-                    None,
-                ),
-                traceback: vec![],
-                previous_exception: None,
-            })
+    pub fn assign(&mut self, slot: usize, value: Value) -> Result<(), Exception> {
+        let slot = &mut self.slots[slot];
+        if slot.value.is_none() {
+            return Err(Exception::from(format!(
+                "variable {} is undefined and cannot be set",
+                slot.name
+            )));
         }
+        slot.value = Some(value);
+        Ok(())
+    }
+
+    // replaces the value if the variable exists
+    pub fn insert(&mut self, name: Symbol, value: Value) {
+        let slot = self.slot(name);
+        self.slots[slot].value = Some(value);
     }
 
-    pub fn define_variable(&mut self, name: String, value: Value) -> Result<(), Exception> {
-        if self.variables.contains_key(&name) {
-            Err(Exception {
-                thrown_object: Value::new(
-                    DynType::Str(format!("variable {} already exists", &name)),
-                    None,
-                ),
-                traceback: vec![],
-                previous_exception: None,
-            })
-        } else {
-            self.variables.insert(name, value);
-            Ok(())
+    pub fn variable(&self, name: Symbol) -> Result<Value, Exception> {
+        match self
+            .find(name)
+            .and_then(|slot| self.slots[slot].value.clone())
+        {
+            Some(value) => Ok(value),
+            None => match &self.outer_scope {
+                Some(outer_scope) => outer_scope.borrow().variable(name),
+                None => Err(undefined_error(name)),
+            },
         }
     }
+
+    pub fn define_variable(&mut self, name: Symbol, value: Value) -> Result<(), Exception> {
+        let slot = self.slot(name);
+        self.define(slot, value)
+    }
+
+    // defined variables of the scope without the outer ones
+    pub fn variables(&self) -> impl Iterator<Item = (Symbol, &Value)> {
+        self.slots
+            .iter()
+            .filter_map(|slot| slot.value.as_ref().map(|value| (slot.name, value)))
+    }
 }
 
 #[derive(Debug)]
@@ -1,56 +1,61 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{collections::HashMap, rc::Rc};
 
 use crate::{
     runtime::{
+        arity::Arity,
+        bytecode::{Instruction, TryBlock},
+        compiler::{Compiler, Variable},
         custom_function::CustomFunction,
-        scope::{Scope, ScopeRef, ScopeState},
+        macros::Macro,
+        modules::{import, ImportNames},
+        scope::ScopeState,
     },
     types::{
         exception::Exception,
         list::{List, ListItem},
+        struct_declare::StructType,
+        symbol::Symbol,
         value::Value,
         DynType,
-        struct_declare::StructType,
     },
 };
 
-use super::calculators::calculate;
+pub type SpecialForms = HashMap<Symbol, SpecialForm>;
 
-pub type SpecialForms = HashMap<String, SpecialForm>;
+// compiles the arguments of the form, the last flag is set for forms in tail position
+type Compile = Rc<dyn Fn(&mut Compiler, Value, Option<(u32, u32)>, bool) -> Result<(), Exception>>;
 
 pub struct SpecialForm {
     pub name: &'static str,
-    calculator: Rc<dyn Fn(Rc<SpecialForms>, ScopeRef, Value) -> Result<Value, Exception>>,
-    possible_scope_state: ScopeState,
+    compile: Compile,
+    // arguments of the form, clauses of `try` are counted too
+    pub arity: Arity,
+    pub possible_scope_state: ScopeState,
 }
 
 impl SpecialForm {
-    pub fn calculate(
+    pub fn compile(
         &self,
-        special_forms: Rc<SpecialForms>,
-        scope: ScopeRef,
+        compiler: &mut Compiler,
         scope_state: ScopeState,
         args: Value,
-        position: Option<(u32, u16)>,
-    ) -> Result<Value, Exception> {
+        position: Option<(u32, u32)>,
+        tail: bool,
+    ) -> Result<(), Exception> {
         if scope_state > self.possible_scope_state {
-            Err(Exception {
-                thrown_object: Value::new(
-                    DynType::Str(format!(
-                        "{} special form is allowed for {:?} scope but {:?} scope is given",
-                        self.name, self.possible_scope_state, scope_state,
-                    )),
-                    None,
+            Err(compiler.error(
+                format!(
+                    "{} special form is allowed for {:?} scope but {:?} scope is given",
+                    self.name, self.possible_scope_state, scope_state,
                 ),
-                traceback: vec![position],
-                previous_exception: None,
-            })
+                position,
+            ))
         } else {
-            match (self.calculator)(special_forms, scope, args) {
+            match (self.compile)(compiler, args, position, tail) {
                 Ok(ok) => Ok(ok),
                 Err(mut err) => {
                     if err.traceback.is_empty() {
-                        err.traceback.push(position);
+                        err.traceback.push(compiler.frame(position));
                     }
                     Err(err)
                 }
@@ -59,59 +64,88 @@ impl SpecialForm {
     }
 }
 
+fn nil() -> Instruction {
+    Instruction::Constant(Value::new(DynType::Nil, None))
+}
+
 fn do_form(
-    special_forms: Rc<SpecialForms>,
-    scope: ScopeRef,
+    compiler: &mut Compiler,
     args: Value,
-) -> Result<Value, Exception> {
+    position: Option<(u32, u32)>,
+    tail: bool,
+) -> Result<(), Exception> {
     let mut list = List::new(args);
-    let local_scope = Rc::new(RefCell::new(Scope::new(Some(scope))));
 
     if let ListItem::End = list.peek() {
-        return Err(Exception {
-            thrown_object: Value::new(DynType::Str(format!("Empty body")), None),
-            traceback: vec![],
-            previous_exception: None,
-        });
+        return Err(Exception::from("Empty body"));
     }
 
-    let mut last = Value::new(DynType::Nil, None);
-    while let ListItem::Middle(expression) = list.next() {
-        last = calculate(
-            special_forms.clone(),
-            local_scope.clone(),
-            ScopeState::Local,
-            expression,
-        )?;
-    }
-    list.next().to_end()?;
-    Ok(last)
+    compiler.begin_block();
+    let result = (|| {
+        while let ListItem::Middle(expression) = list.next() {
+            if let ListItem::End = list.peek() {
+                return compiler.compile(expression, ScopeState::Local, tail);
+            }
+            compiler.compile(expression, ScopeState::Local, false)?;
+            compiler.emit(Instruction::Pop, position);
+        }
+        list.next().to_end()
+    })();
+    compiler.end_block();
+    result
 }
 
 fn let_form(
-    special_forms: Rc<SpecialForms>,
-    scope: ScopeRef,
+    compiler: &mut Compiler,
     args: Value,
-) -> Result<Value, Exception> {
+    position: Option<(u32, u32)>,
+    _: bool,
+) -> Result<(), Exception> {
     let mut list = List::new(args);
     let name = list.next().to_middle()?.content.to_symbol()?;
     let value_expr = list.next().to_middle()?;
     list.next().to_end()?;
-    let new_var = calculate(
-        special_forms,
-        scope.clone(),
-        ScopeState::Expression,
-        value_expr,
-    )?;
-    (&mut *scope.borrow_mut()).define_variable(name, new_var)?;
-    scope.borrow().variable(&String::from("nil"))
+
+    compiler.compile(value_expr, ScopeState::Expression, false)?;
+    let store = compiler.define(name, position)?;
+    compiler.emit(store, position);
+    compiler.emit(nil(), position);
+    Ok(())
+}
+
+fn set_form(
+    compiler: &mut Compiler,
+    args: Value,
+    position: Option<(u32, u32)>,
+    _: bool,
+) -> Result<(), Exception> {
+    let mut list = List::new(args);
+    let name = list.next().to_middle()?.content.to_symbol()?;
+    let value_expr = list.next().to_middle()?;
+    list.next().to_end()?;
+
+    compiler.compile(value_expr, ScopeState::Expression, false)?;
+    let store = match compiler.resolve(name, position, true) {
+        Variable::Local(slot) => Instruction::SetLocal(slot),
+        Variable::Outer(depth, slot) => Instruction::SetOuter(depth, slot),
+        Variable::Global(slot, name) => Instruction::SetGlobal(slot, name),
+        // builtins are shared by every module
+        Variable::Builtin(..) => {
+            return Err(compiler.error(format!("built-in {} cannot be set", name), position))
+        }
+        Variable::Macro(_) => return Err(Exception::from(format!("macro {} cannot be set", name))),
+    };
+    compiler.emit(store, position);
+    compiler.emit(nil(), position);
+    Ok(())
 }
 
 fn def_form(
-    special_forms: Rc<SpecialForms>,
-    scope: ScopeRef,
+    compiler: &mut Compiler,
     args: Value,
-) -> Result<Value, Exception> {
+    position: Option<(u32, u32)>,
+    _: bool,
+) -> Result<(), Exception> {
     let mut list = List::new(args);
 
     let mut arguments = List::new(list.next().to_middle()?);
@@ -119,43 +153,82 @@ fn def_form(
     let body = list.next().to_middle()?;
     list.next().to_end()?;
 
-    let function = CustomFunction::new(body, scope.clone(), arguments.current_value);
+    // the variable is declared first, so the function can call itself
+    let store = compiler.define(name, position)?;
+    let prototype = compiler.function(&name.name(), arguments.current_value, body, position)?;
+    compiler.emit(Instruction::Closure(prototype), position);
+    compiler.emit(store, position);
+    compiler.emit(nil(), position);
+    Ok(())
+}
 
-    scope.borrow_mut().define_variable(
-        name,
-        Value::new(
-            DynType::Closure(Rc::new(move |args| {
-                function.call(special_forms.clone(), args)
-            })),
-            None,
-        ),
-    )?;
+// Macros are defined during the compilation, so they expand the code which goes after them.
+// Transformers see only the global variables, the local ones don't exist yet.
+fn macro_form(
+    compiler: &mut Compiler,
+    args: Value,
+    position: Option<(u32, u32)>,
+    hygienic: bool,
+) -> Result<(), Exception> {
+    let mut list = List::new(args);
+
+    let mut arguments = List::new(list.next().to_middle()?);
+    let name = arguments.next().to_middle()?.content.to_symbol()?;
+    let body = list.next().to_middle()?;
+    list.next().to_end()?;
+
+    let globals = compiler.globals();
+    let mut transformer_compiler = Compiler::new(compiler.special_forms(), globals.clone());
+    let prototype =
+        transformer_compiler.function(&name.name(), arguments.current_value, body, position)?;
+    compiler.merge(transformer_compiler);
+    let function = CustomFunction::new(prototype, None, globals);
+
+    compiler.define_macro(name, Macro::new(function, hygienic), position)?;
+    compiler.emit(nil(), position);
+    Ok(())
+}
+
+fn macroexpand_form(
+    compiler: &mut Compiler,
+    args: Value,
+    position: Option<(u32, u32)>,
+    _: bool,
+) -> Result<(), Exception> {
+    let mut list = List::new(args);
+    let mut form = list.next().to_middle()?;
+    list.next().to_end()?;
 
-    Ok(Value::new(DynType::Nil, None))
+    while let Some(macro_value) = compiler.macro_of(&form) {
+        form = macro_value.expand(form.content.to_pair()?.right.clone(), form.position)?;
+    }
+    compiler.emit(Instruction::Constant(form), position);
+    Ok(())
 }
 
 fn lambda_form(
-    special_forms: Rc<SpecialForms>,
-    scope: ScopeRef,
+    compiler: &mut Compiler,
     args: Value,
-) -> Result<Value, Exception> {
+    position: Option<(u32, u32)>,
+    _: bool,
+) -> Result<(), Exception> {
     let mut list = List::new(args);
 
     let arguments = list.next().to_middle()?;
     let body = list.next().to_middle()?;
     list.next().to_end()?;
 
-    let function = CustomFunction::new(body, scope.clone(), arguments);
-
-    Ok(Value::new(
-        DynType::Closure(Rc::new(move |args| {
-            function.call(special_forms.clone(), args)
-        })),
-        None,
-    ))
+    let prototype = compiler.function("lambda", arguments, body, position)?;
+    compiler.emit(Instruction::Closure(prototype), position);
+    Ok(())
 }
 
-fn struct_form(_: Rc<SpecialForms>, scope: ScopeRef, args: Value) -> Result<Value, Exception> {
+fn struct_form(
+    compiler: &mut Compiler,
+    args: Value,
+    position: Option<(u32, u32)>,
+    _: bool,
+) -> Result<(), Exception> {
     let mut list = List::new(args);
     let name = list.next().to_middle()?.content.to_symbol()?;
     let mut fields_list = List::new(list.next().to_middle()?);
@@ -165,101 +238,239 @@ fn struct_form(_: Rc<SpecialForms>, scope: ScopeRef, args: Value) -> Result<Valu
     while let ListItem::Middle(field) = fields_list.next() {
         fields.push(field.content.to_symbol()?)
     }
-    scope.borrow_mut().define_variable(
-        name.clone(),
-        Value::new(
-            DynType::StructDeclare(Rc::new(StructType { name, fields })),
-            None,
-        ),
-    )?;
-    Ok(Value::new(DynType::Nil, None))
+    let declaration = Value::new(
+        DynType::StructDeclare(Rc::new(StructType { name, fields })),
+        None,
+    );
+    let store = compiler.define(name, position)?;
+    compiler.emit(Instruction::Constant(declaration), position);
+    compiler.emit(store, position);
+    compiler.emit(nil(), position);
+    Ok(())
 }
 
 fn get_field_form(
-    special_forms: Rc<SpecialForms>,
-    scope: ScopeRef,
+    compiler: &mut Compiler,
     args: Value,
-) -> Result<Value, Exception> {
+    position: Option<(u32, u32)>,
+    _: bool,
+) -> Result<(), Exception> {
     let mut list = List::new(args);
     let value_expr = list.next().to_middle()?;
     let required_field = list.next().to_middle()?.content.to_symbol()?;
     list.next().to_end()?;
 
-    let new_var = calculate(
-        special_forms,
-        scope.clone(),
-        ScopeState::Expression,
-        value_expr,
-    )?;
-
-    new_var.content.to_struct()?.get_field(required_field)
+    compiler.compile(value_expr, ScopeState::Expression, false)?;
+    compiler.emit(Instruction::GetField(required_field), position);
+    Ok(())
 }
 
 fn if_form(
-    special_forms: Rc<SpecialForms>,
-    scope: ScopeRef,
+    compiler: &mut Compiler,
     args: Value,
-) -> Result<Value, Exception> {
+    position: Option<(u32, u32)>,
+    tail: bool,
+) -> Result<(), Exception> {
     let mut list = List::new(args);
     let condition = list.next().to_middle()?;
     let main_body = list.next().to_middle()?;
     let else_body = list.next().to_middle()?;
     list.next().to_end()?;
-    if let DynType::Nil = &*calculate(
-        special_forms.clone(),
-        scope.clone(),
-        ScopeState::Expression,
-        condition,
-    )?
-    .content
-    {
-        calculate(special_forms, scope, ScopeState::Expression, else_body)
-    } else {
-        calculate(special_forms, scope, ScopeState::Expression, main_body)
-    }
+
+    compiler.compile(condition, ScopeState::Expression, false)?;
+    let else_jump = compiler.emit(Instruction::JumpIfFalse(0), position);
+    compiler.compile(main_body, ScopeState::Expression, tail)?;
+    let end_jump = compiler.emit(Instruction::Jump(0), position);
+    compiler.patch_jump(else_jump);
+    compiler.compile(else_body, ScopeState::Expression, tail)?;
+    compiler.patch_jump(end_jump);
+    Ok(())
 }
 
-fn and_form(
-    special_forms: Rc<SpecialForms>,
-    scope: ScopeRef,
+// `and` stops at the first false value and `or` at the first true one, the value is the result
+fn logic_form(
+    compiler: &mut Compiler,
     args: Value,
-) -> Result<Value, Exception> {
+    position: Option<(u32, u32)>,
+    tail: bool,
+    is_and: bool,
+) -> Result<(), Exception> {
     let mut list = List::new(args);
+    let mut end_jumps = vec![];
     while let ListItem::Middle(parameter) = list.next() {
-        if let DynType::Nil = &*calculate(
-            special_forms.clone(),
-            scope.clone(),
-            ScopeState::Expression,
-            parameter,
-        )?
-        .content
-        {
-            return Ok(Value::new(DynType::Nil, None));
+        if let ListItem::End = list.peek() {
+            compiler.compile(parameter, ScopeState::Expression, tail)?;
+            for jump in end_jumps {
+                compiler.patch_jump(jump);
+            }
+            return Ok(());
         }
+        compiler.compile(parameter, ScopeState::Expression, false)?;
+        end_jumps.push(compiler.emit(
+            if is_and {
+                Instruction::JumpIfFalseOrPop(0)
+            } else {
+                Instruction::JumpIfTrueOrPop(0)
+            },
+            position,
+        ));
+    }
+    list.next().to_end()?;
+
+    compiler.emit(
+        Instruction::Constant(Value::new(DynType::Bool(is_and), None)),
+        position,
+    );
+    for jump in end_jumps {
+        compiler.patch_jump(jump);
     }
-    Ok(Value::new(DynType::Number(1.0), None))
+    Ok(())
+}
+
+fn and_form(
+    compiler: &mut Compiler,
+    args: Value,
+    position: Option<(u32, u32)>,
+    tail: bool,
+) -> Result<(), Exception> {
+    logic_form(compiler, args, position, tail, true)
 }
 
 fn or_form(
-    special_forms: Rc<SpecialForms>,
-    scope: ScopeRef,
+    compiler: &mut Compiler,
     args: Value,
-) -> Result<Value, Exception> {
+    position: Option<(u32, u32)>,
+    tail: bool,
+) -> Result<(), Exception> {
+    logic_form(compiler, args, position, tail, false)
+}
+
+fn throw_form(
+    compiler: &mut Compiler,
+    args: Value,
+    position: Option<(u32, u32)>,
+    _: bool,
+) -> Result<(), Exception> {
     let mut list = List::new(args);
-    while let ListItem::Middle(parameter) = list.next() {
-        if let DynType::Nil = &*calculate(
-            special_forms.clone(),
-            scope.clone(),
-            ScopeState::Expression,
-            parameter,
-        )?
-        .content
-        {
-        } else {
-            return Ok(Value::new(DynType::Number(1.0), None));
+    let thrown_expr = list.next().to_middle()?;
+    list.next().to_end()?;
+
+    compiler.compile(thrown_expr, ScopeState::Expression, false)?;
+    compiler.emit(Instruction::Throw, position);
+    Ok(())
+}
+
+fn try_form(
+    compiler: &mut Compiler,
+    args: Value,
+    position: Option<(u32, u32)>,
+    _: bool,
+) -> Result<(), Exception> {
+    let mut list = List::new(args);
+    let body = list.next().to_middle()?;
+
+    // (catch symbol expression) and (finally expression) clauses
+    let mut catch_clause = None;
+    let mut finally_clause = None;
+    while let ListItem::Middle(clause) = list.next() {
+        let mut clause_list = List::new(clause.clone());
+        let clause_name = clause_list.next().to_middle()?.content.to_symbol()?;
+        match &*clause_name.name() {
+            "catch" if catch_clause.is_none() && finally_clause.is_none() => {
+                let name = clause_list.next().to_middle()?.content.to_symbol()?;
+                let handler = clause_list.next().to_middle()?;
+                clause_list.next().to_end()?;
+                catch_clause = Some((name, handler));
+            }
+            "finally" if finally_clause.is_none() => {
+                let cleanup = clause_list.next().to_middle()?;
+                clause_list.next().to_end()?;
+                finally_clause = Some(cleanup);
+            }
+            _ => {
+                return Err(compiler.error(
+                    format!("Unexpected try clause {}", clause.content),
+                    clause.position,
+                ))
+            }
         }
     }
-    Ok(Value::new(DynType::Nil, None))
+    list.next().to_end()?;
+
+    if catch_clause.is_none() && finally_clause.is_none() {
+        return Err(Exception::from("try form needs catch or finally clause"));
+    }
+
+    let body =
+        compiler.block_chunk(|compiler| compiler.compile(body, ScopeState::Expression, false))?;
+    let catch = match catch_clause {
+        Some((name, handler)) => {
+            compiler.begin_block();
+            let result = compiler.declare_local(name, position).and_then(|slot| {
+                let handler = compiler.block_chunk(|compiler| {
+                    compiler.compile(handler, ScopeState::Expression, false)
+                })?;
+                Ok((slot, handler))
+            });
+            compiler.end_block();
+            Some(result?)
+        }
+        None => None,
+    };
+    let finally = match finally_clause {
+        Some(cleanup) => Some(
+            compiler
+                .block_chunk(|compiler| compiler.compile(cleanup, ScopeState::Expression, false))?,
+        ),
+        None => None,
+    };
+
+    compiler.emit(
+        Instruction::Try(Rc::new(TryBlock {
+            body,
+            catch,
+            finally,
+        })),
+        position,
+    );
+    Ok(())
+}
+
+fn import_form(
+    compiler: &mut Compiler,
+    args: Value,
+    position: Option<(u32, u32)>,
+    _: bool,
+) -> Result<(), Exception> {
+    let mut list = List::new(args);
+    let path = match &*list.next().to_middle()?.content {
+        DynType::Str(path) => path.clone(),
+        other => {
+            return Err(Exception::from(format!(
+                "Expected module path, given {}",
+                other
+            )))
+        }
+    };
+    let names = match list.next() {
+        ListItem::Middle(names) if matches!(&*names.content, DynType::Pair(_)) => {
+            let mut names = List::new(names);
+            let mut listed = vec![];
+            while let ListItem::Middle(name) = names.next() {
+                listed.push(name.content.to_symbol()?);
+            }
+            names.next().to_end()?;
+            ImportNames::Listed(listed)
+        }
+        ListItem::Middle(prefix) => ImportNames::Prefixed(Some(prefix.content.to_symbol()?)),
+        _ => ImportNames::Prefixed(None),
+    };
+    list.next().to_end()?;
+
+    // imported names are defined before the code which uses them is compiled
+    import(compiler.special_forms(), &compiler.globals(), &path, &names)?;
+    compiler.emit(nil(), position);
+    Ok(())
 }
 
 pub fn all_special_forms() -> Rc<SpecialForms> {
@@ -267,96 +478,180 @@ pub fn all_special_forms() -> Rc<SpecialForms> {
 
     let let_form_name = "let";
     special_forms.insert(
-        let_form_name.to_string(),
+        Symbol::new(let_form_name),
         SpecialForm {
             name: let_form_name,
-            calculator: Rc::new(|special_forms, scope, args| let_form(special_forms, scope, args)),
+            compile: Rc::new(let_form),
+            arity: Arity::exact(2),
             possible_scope_state: ScopeState::Local,
         },
     );
 
+    let set_form_name = "set";
+    special_forms.insert(
+        Symbol::new(set_form_name),
+        SpecialForm {
+            name: set_form_name,
+            compile: Rc::new(set_form),
+            arity: Arity::exact(2),
+            possible_scope_state: ScopeState::Expression,
+        },
+    );
+
     let def_form_name = "def";
     special_forms.insert(
-        def_form_name.to_string(),
+        Symbol::new(def_form_name),
         SpecialForm {
             name: def_form_name,
-            calculator: Rc::new(|special_forms, scope, args| def_form(special_forms, scope, args)),
+            compile: Rc::new(def_form),
+            arity: Arity::exact(2),
             possible_scope_state: ScopeState::Local,
         },
     );
 
+    let defmacro_form_name = "defmacro";
+    special_forms.insert(
+        Symbol::new(defmacro_form_name),
+        SpecialForm {
+            name: defmacro_form_name,
+            compile: Rc::new(|compiler, args, position, _| {
+                macro_form(compiler, args, position, false)
+            }),
+            arity: Arity::exact(2),
+            possible_scope_state: ScopeState::Local,
+        },
+    );
+
+    let defsyntax_form_name = "defsyntax";
+    special_forms.insert(
+        Symbol::new(defsyntax_form_name),
+        SpecialForm {
+            name: defsyntax_form_name,
+            compile: Rc::new(|compiler, args, position, _| {
+                macro_form(compiler, args, position, true)
+            }),
+            arity: Arity::exact(2),
+            possible_scope_state: ScopeState::Local,
+        },
+    );
+
+    let macroexpand_form_name = "macroexpand";
+    special_forms.insert(
+        Symbol::new(macroexpand_form_name),
+        SpecialForm {
+            name: macroexpand_form_name,
+            compile: Rc::new(macroexpand_form),
+            arity: Arity::exact(1),
+            possible_scope_state: ScopeState::Expression,
+        },
+    );
+
     let lambda_form_name = "lambda";
     special_forms.insert(
-        lambda_form_name.to_string(),
+        Symbol::new(lambda_form_name),
         SpecialForm {
             name: lambda_form_name,
-            calculator: Rc::new(|special_forms, scope, args| {
-                lambda_form(special_forms, scope, args)
-            }),
+            compile: Rc::new(lambda_form),
+            arity: Arity::exact(2),
             possible_scope_state: ScopeState::Expression,
         },
     );
 
     let do_form_name = "do";
     special_forms.insert(
-        do_form_name.to_string(),
+        Symbol::new(do_form_name),
         SpecialForm {
             name: do_form_name,
-            calculator: Rc::new(|special_forms, scope, args| do_form(special_forms, scope, args)),
+            compile: Rc::new(do_form),
+            arity: Arity::at_least(1),
             possible_scope_state: ScopeState::Expression,
         },
     );
 
     let struct_form_name = "struct";
     special_forms.insert(
-        struct_form_name.to_string(),
+        Symbol::new(struct_form_name),
         SpecialForm {
             name: struct_form_name,
-            calculator: Rc::new(|special_forms, scope, args| {
-                struct_form(special_forms, scope, args)
-            }),
+            compile: Rc::new(struct_form),
+            arity: Arity::exact(2),
             possible_scope_state: ScopeState::Global,
         },
     );
 
     let if_form_name = "if";
     special_forms.insert(
-        if_form_name.to_string(),
+        Symbol::new(if_form_name),
         SpecialForm {
             name: if_form_name,
-            calculator: Rc::new(|special_forms, scope, args| if_form(special_forms, scope, args)),
+            compile: Rc::new(if_form),
+            arity: Arity::exact(3),
             possible_scope_state: ScopeState::Expression,
         },
     );
 
     let and_form_name = "and";
     special_forms.insert(
-        and_form_name.to_string(),
+        Symbol::new(and_form_name),
         SpecialForm {
             name: and_form_name,
-            calculator: Rc::new(|special_forms, scope, args| and_form(special_forms, scope, args)),
+            compile: Rc::new(and_form),
+            arity: Arity::any(),
             possible_scope_state: ScopeState::Expression,
         },
     );
 
     let or_form_name = "or";
     special_forms.insert(
-        or_form_name.to_string(),
+        Symbol::new(or_form_name),
         SpecialForm {
             name: or_form_name,
-            calculator: Rc::new(|special_forms, scope, args| or_form(special_forms, scope, args)),
+            compile: Rc::new(or_form),
+            arity: Arity::any(),
             possible_scope_state: ScopeState::Expression,
         },
     );
 
     let get_field_form_name = "::";
     special_forms.insert(
-        get_field_form_name.to_string(),
+        Symbol::new(get_field_form_name),
         SpecialForm {
             name: get_field_form_name,
-            calculator: Rc::new(|special_forms, scope, args| {
-                get_field_form(special_forms, scope, args)
-            }),
+            compile: Rc::new(get_field_form),
+            arity: Arity::exact(2),
+            possible_scope_state: ScopeState::Expression,
+        },
+    );
+
+    let import_form_name = "import";
+    special_forms.insert(
+        Symbol::new(import_form_name),
+        SpecialForm {
+            name: import_form_name,
+            compile: Rc::new(import_form),
+            arity: Arity::range(1, 2),
+            possible_scope_state: ScopeState::Global,
+        },
+    );
+
+    let throw_form_name = "throw";
+    special_forms.insert(
+        Symbol::new(throw_form_name),
+        SpecialForm {
+            name: throw_form_name,
+            compile: Rc::new(throw_form),
+            arity: Arity::exact(1),
+            possible_scope_state: ScopeState::Expression,
+        },
+    );
+
+    let try_form_name = "try";
+    special_forms.insert(
+        Symbol::new(try_form_name),
+        SpecialForm {
+            name: try_form_name,
+            compile: Rc::new(try_form),
+            arity: Arity::range(2, 3),
             possible_scope_state: ScopeState::Expression,
         },
     );
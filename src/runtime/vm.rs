@@ -0,0 +1,556 @@
+use std::rc::Rc;
+
+use crate::{
+    types::{
+        exception::{Exception, Frame},
+        list::{list_of, List, ListItem},
+        map::Map,
+        value::Value,
+        DynType,
+    },
+    Error,
+};
+
+use super::{
+    bytecode::{Chunk, Instruction, Prototype, Quote, TryBlock},
+    compiler::Compiler,
+    custom_function::{CustomFunction, Env},
+    scope::ScopeRef,
+    special_forms::SpecialForms,
+};
+
+struct CallFrame {
+    chunk: Rc<Chunk>,
+    ip: usize,
+    env: Rc<Env>,
+    globals: ScopeRef,
+    // height of the stack before the call
+    base: usize,
+    // tail calls replace each other, so only the latest one is in the traceback
+    tail_call: Option<Frame>,
+    // blocks of a try form run as frames of the same machine, so recursion through them
+    // doesn't take the rust stack
+    handler: Option<Handler>,
+}
+
+// the part of the try form which the frame runs
+enum Stage {
+    Body,
+    // the caught exception becomes the cause of an exception of the handler
+    Catch(Exception),
+    // the result of the try form or the exception which is thrown again after the cleanup
+    Finally(Result<Value, Exception>),
+}
+
+struct Handler {
+    block: Rc<TryBlock>,
+    stage: Stage,
+}
+
+struct Machine {
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+}
+
+fn nil() -> Value {
+    Value::new(DynType::Nil, None)
+}
+
+fn not_a_function(value: &Value) -> Exception {
+    match &*value.content {
+        DynType::Macro(_) => Exception::from("macro is used before its definition"),
+        content => Exception::from(format!("{} is not a function or special form", content)),
+    }
+}
+
+// Compiles and runs the top-level expressions one by one and gives their results to the callback,
+// so macros and imports see the effects of the expressions above them. An expression isn't run
+// when it uses an undefined variable, uses in the functions are checked at the end.
+pub fn eval(
+    special_forms: Rc<SpecialForms>,
+    globals: ScopeRef,
+    values: Vec<Value>,
+    mut each: impl FnMut(Value),
+) -> Result<(), Error> {
+    let mut compiler = Compiler::new(special_forms, globals.clone());
+    for value in values {
+        let prototype = compiler.compile_top_level(value)?;
+        compiler.check().map_err(Error::Undefined)?;
+        each(run(&prototype, globals.clone())?);
+    }
+    compiler.finish().map_err(Error::Undefined)
+}
+
+pub fn run(prototype: &Prototype, globals: ScopeRef) -> Result<Value, Exception> {
+    let env = Rc::new(Env::new(prototype.slots, None));
+    execute(prototype.chunk.clone(), env, globals)
+}
+
+pub fn call(function: &CustomFunction, args: Vec<Value>) -> Result<Value, Exception> {
+    let env = function.prepare_env(args)?;
+    execute(function.prototype().chunk.clone(), env, function.globals())
+}
+
+fn execute(chunk: Rc<Chunk>, env: Rc<Env>, globals: ScopeRef) -> Result<Value, Exception> {
+    let mut machine = Machine {
+        stack: vec![],
+        frames: vec![CallFrame {
+            chunk,
+            ip: 0,
+            env,
+            globals,
+            base: 0,
+            tail_call: None,
+            handler: None,
+        }],
+    };
+    loop {
+        match machine.execute_frame() {
+            Ok(Some(value)) => return Ok(value),
+            Ok(None) => {}
+            Err(err) => machine.throw(err)?,
+        }
+    }
+}
+
+impl Machine {
+    fn pop(&mut self) -> Value {
+        self.stack.pop().unwrap()
+    }
+
+    fn pop_many(&mut self, count: usize) -> Vec<Value> {
+        self.stack.split_off(self.stack.len() - count)
+    }
+
+    // Runs the code of the latest call until another call starts or the call returns.
+    // The value is returned when the first call of the machine is finished.
+    fn execute_frame(&mut self) -> Result<Option<Value>, Exception> {
+        let frame = self.frames.last().unwrap();
+        let chunk = frame.chunk.clone();
+        let env = frame.env.clone();
+        let globals = frame.globals.clone();
+        let builtins = globals.borrow().outer_scope.clone();
+        let mut ip = frame.ip;
+
+        loop {
+            let instruction = &chunk.code[ip];
+            ip += 1;
+            self.frames.last_mut().unwrap().ip = ip;
+
+            match instruction {
+                Instruction::Constant(value) => self.stack.push(value.clone()),
+                Instruction::Pop => {
+                    self.pop();
+                }
+                Instruction::GetLocal(slot) => {
+                    let value = env.slots.borrow()[*slot].clone();
+                    self.stack.push(value);
+                }
+                Instruction::SetLocal(slot) => {
+                    let value = self.pop();
+                    env.slots.borrow_mut()[*slot] = value;
+                }
+                Instruction::GetOuter(depth, slot) => {
+                    let value = env.outer(*depth).slots.borrow()[*slot].clone();
+                    self.stack.push(value);
+                }
+                Instruction::SetOuter(depth, slot) => {
+                    let value = self.pop();
+                    env.outer(*depth).slots.borrow_mut()[*slot] = value;
+                }
+                Instruction::GetGlobal(slot, _) => {
+                    let value = globals.borrow().get(*slot)?;
+                    self.stack.push(value);
+                }
+                Instruction::DefineGlobal(slot, _) => {
+                    let value = self.pop();
+                    globals.borrow_mut().define(*slot, value)?;
+                }
+                Instruction::SetGlobal(slot, _) => {
+                    let value = self.pop();
+                    globals.borrow_mut().assign(*slot, value)?;
+                }
+                Instruction::GetBuiltin(slot, _) => {
+                    let value = builtins.as_ref().unwrap().borrow().get(*slot)?;
+                    self.stack.push(value);
+                }
+                Instruction::Jump(target) => ip = *target,
+                Instruction::JumpIfFalse(target) => {
+                    if !self.pop().content.is_truthy() {
+                        ip = *target;
+                    }
+                }
+                Instruction::JumpIfFalseOrPop(target) => {
+                    if !self.stack.last().unwrap().content.is_truthy() {
+                        ip = *target;
+                    } else {
+                        self.pop();
+                    }
+                }
+                Instruction::JumpIfTrueOrPop(target) => {
+                    if self.stack.last().unwrap().content.is_truthy() {
+                        ip = *target;
+                    } else {
+                        self.pop();
+                    }
+                }
+                Instruction::Call(count) => {
+                    let args = self.pop_many(*count);
+                    let callee = self.pop();
+                    match &*callee.content {
+                        DynType::Function(function) => {
+                            let env = function.prepare_env(args)?;
+                            self.frames.push(CallFrame {
+                                chunk: function.prototype().chunk.clone(),
+                                ip: 0,
+                                env,
+                                globals: function.globals(),
+                                base: self.stack.len(),
+                                tail_call: None,
+                                handler: None,
+                            });
+                            return Ok(None);
+                        }
+                        DynType::Closure(closure) => {
+                            let result = closure(list_of(args, nil()))?;
+                            self.stack.push(result);
+                        }
+                        _ => return Err(not_a_function(&callee)),
+                    }
+                }
+                Instruction::TailCall(count) => {
+                    let args = self.pop_many(*count);
+                    let callee = self.pop();
+                    let result = match &*callee.content {
+                        DynType::Function(function) => {
+                            let env = function.prepare_env(args)?;
+                            let frame = self.frames.last_mut().unwrap();
+                            self.stack.truncate(frame.base);
+                            frame.tail_call = Some(Frame {
+                                file: chunk.file.clone(),
+                                position: chunk.positions[ip - 1],
+                            });
+                            frame.chunk = function.prototype().chunk.clone();
+                            frame.ip = 0;
+                            frame.env = env;
+                            frame.globals = function.globals();
+                            return Ok(None);
+                        }
+                        DynType::Closure(closure) => closure(list_of(args, nil()))?,
+                        _ => return Err(not_a_function(&callee)),
+                    };
+                    return self.return_value(result);
+                }
+                Instruction::Return => {
+                    let result = self.pop();
+                    return self.return_value(result);
+                }
+                Instruction::Closure(prototype) => self.stack.push(Value::new(
+                    DynType::Function(Rc::new(CustomFunction::new(
+                        prototype.clone(),
+                        Some(env.clone()),
+                        globals.clone(),
+                    ))),
+                    None,
+                )),
+                Instruction::GetField(name) => {
+                    let value = self.pop();
+                    self.stack
+                        .push(value.content.to_struct()?.get_field(*name)?);
+                }
+                Instruction::BuildList(spliced) => {
+                    let tail = self.pop();
+                    let items = self.pop_many(spliced.len());
+                    let mut list = vec![];
+                    for (item, spliced) in items.into_iter().zip(spliced.iter()) {
+                        if *spliced {
+                            let mut spliced_list = List::new(item);
+                            while let ListItem::Middle(spliced_item) = spliced_list.next() {
+                                list.push(spliced_item);
+                            }
+                            spliced_list.next().to_end()?;
+                        } else {
+                            list.push(item);
+                        }
+                    }
+                    let list = list_of(list, tail);
+                    self.stack.push(Value {
+                        content: list.content,
+                        position: chunk.positions[ip - 1],
+                    });
+                }
+                Instruction::BuildMap(keys) => {
+                    let values = self.pop_many(keys.len());
+                    let entries = keys.iter().cloned().zip(values).collect();
+                    self.stack.push(Value::new(
+                        DynType::Map(Map::new(entries)),
+                        chunk.positions[ip - 1],
+                    ));
+                }
+                Instruction::BuildVector(count) => {
+                    let items = self.pop_many(*count);
+                    self.stack.push(Value::new(
+                        DynType::Vector(Rc::new(items)),
+                        chunk.positions[ip - 1],
+                    ));
+                }
+                Instruction::Wrap(quote) => {
+                    let value = self.pop();
+                    self.stack.push(Value::new(
+                        match quote {
+                            Quote::Quasiquote => DynType::Quasiquoted(value),
+                            Quote::Unquote => DynType::Unquoted(value),
+                            Quote::UnquoteSplicing => DynType::UnquoteSpliced(value),
+                        },
+                        chunk.positions[ip - 1],
+                    ));
+                }
+                Instruction::Throw => return Err(Exception::new(self.pop())),
+                Instruction::Try(block) => {
+                    let handler = Handler {
+                        block: block.clone(),
+                        stage: Stage::Body,
+                    };
+                    self.begin_stage(&env, &globals, block.body.clone(), handler);
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    // runs a block of the try form in the environment of the frame which has the form
+    fn begin_stage(
+        &mut self,
+        env: &Rc<Env>,
+        globals: &ScopeRef,
+        chunk: Rc<Chunk>,
+        handler: Handler,
+    ) {
+        self.frames.push(CallFrame {
+            chunk,
+            ip: 0,
+            env: env.clone(),
+            globals: globals.clone(),
+            base: self.stack.len(),
+            tail_call: None,
+            handler: Some(handler),
+        });
+    }
+
+    // Finishes the latest call, the result goes to the caller. A finished body or handler
+    // of a try form goes to its finally block first.
+    fn return_value(&mut self, result: Value) -> Result<Option<Value>, Exception> {
+        let mut frame = self.frames.pop().unwrap();
+        self.stack.truncate(frame.base);
+        if let Some(handler) = frame.handler.take() {
+            match (handler.stage, &handler.block.finally) {
+                (Stage::Finally(pending), _) => {
+                    self.stack.push(pending?);
+                    return Ok(None);
+                }
+                (_, Some(cleanup)) => {
+                    let handler = Handler {
+                        stage: Stage::Finally(Ok(result)),
+                        block: handler.block.clone(),
+                    };
+                    self.begin_stage(&frame.env, &frame.globals, cleanup.clone(), handler);
+                    return Ok(None);
+                }
+                (_, None) => {}
+            }
+        }
+        if self.frames.is_empty() {
+            Ok(Some(result))
+        } else {
+            self.stack.push(result);
+            Ok(None)
+        }
+    }
+
+    // Unwinds the calls till a try form which handles the exception. Positions of the unwound calls
+    // are added to the traceback, the failed instruction is added only when the exception comes
+    // without a traceback. The exception is returned when nothing handles it.
+    fn throw(&mut self, mut err: Exception) -> Result<(), Exception> {
+        let mut is_first = true;
+        while let Some(mut frame) = self.frames.pop() {
+            let is_call = matches!(
+                frame.chunk.code[frame.ip - 1],
+                Instruction::Call(_) | Instruction::TailCall(_)
+            );
+            if is_call || is_first && err.traceback.is_empty() {
+                err.traceback.push(Frame {
+                    file: frame.chunk.file.clone(),
+                    position: frame.chunk.positions[frame.ip - 1],
+                });
+            }
+            if let Some(tail_call) = &frame.tail_call {
+                err.traceback.push(tail_call.clone());
+            }
+            is_first = false;
+            self.stack.truncate(frame.base);
+
+            let handler = match frame.handler.take() {
+                Some(handler) => handler,
+                None => continue,
+            };
+            match handler.stage {
+                Stage::Body => {
+                    if let Some((slot, catch)) = &handler.block.catch {
+                        frame.env.slots.borrow_mut()[*slot] = err.thrown_object.clone();
+                        let handler = Handler {
+                            stage: Stage::Catch(err),
+                            block: handler.block.clone(),
+                        };
+                        self.begin_stage(&frame.env, &frame.globals, catch.clone(), handler);
+                        return Ok(());
+                    }
+                }
+                Stage::Catch(caught) => err = err.caused_by(caught),
+                Stage::Finally(Err(pending)) => {
+                    err = err.caused_by(pending);
+                    continue;
+                }
+                Stage::Finally(Ok(_)) => continue,
+            }
+            if let Some(cleanup) = handler.block.finally.clone() {
+                let handler = Handler {
+                    stage: Stage::Finally(Err(err)),
+                    block: handler.block,
+                };
+                self.begin_stage(&frame.env, &frame.globals, cleanup, handler);
+                return Ok(());
+            }
+        }
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        types::{exception::Exception, value::Value, DynType},
+        Error, Interpreter,
+    };
+
+    fn eval(code: &str) -> Value {
+        Interpreter::new().eval_str(code).unwrap()
+    }
+
+    fn integer(code: &str) -> i64 {
+        match *eval(code).content {
+            DynType::Integer(value) => value,
+            ref other => panic!("expected an integer, given {:?}", other),
+        }
+    }
+
+    fn fail(code: &str) -> Exception {
+        match Interpreter::new().eval_str(code) {
+            Err(Error::Runtime(err)) => err,
+            Err(err) => panic!("expected an exception, given {:?}", err),
+            Ok(value) => panic!("expected an exception, given {:?}", value.content),
+        }
+    }
+
+    #[test]
+    fn tail_calls_keep_the_stack_flat() {
+        let code = "
+            (def (count n acc) (if (= n 0) acc (count (- n 1) (+ acc 1))))
+            (count 1000000 0)";
+        assert_eq!(*eval(code).content, DynType::Integer(1_000_000));
+    }
+
+    #[test]
+    fn closures_share_captured_variables() {
+        let code = "
+            (def (make_counter) (do
+                (let n 0)
+                (lambda () (do (set n (+ n 1)) n))))
+            (let counter (make_counter))
+            (counter)
+            (counter)
+            (+ (* (counter) 10) ((make_counter)))";
+        assert_eq!(integer(code), 31);
+    }
+
+    #[test]
+    fn try_runs_catch_then_finally() {
+        let code = "
+            (let steps 0)
+            (def (step n) (set steps (+ (* steps 10) n)))
+            (let caught (try (do (step 1) (throw 5) (step 9)) (catch e (do (step 2) e)) (finally (step 3))))
+            (+ (* steps 10) caught)";
+        assert_eq!(integer(code), 1235);
+        assert_eq!(integer("(try (+ 1 2) (catch e 0) (finally 10))"), 3);
+    }
+
+    #[test]
+    fn exceptions_of_handlers_keep_the_cause() {
+        let err = fail("(try (throw 1) (catch e (throw (+ e 1))) (finally 0))");
+        assert_eq!(*err.thrown_object.content, DynType::Integer(2));
+        let cause = err.previous_exception.unwrap();
+        assert_eq!(*cause.thrown_object.content, DynType::Integer(1));
+
+        let err = fail("(try (throw 1) (finally (throw 2)))");
+        assert_eq!(*err.thrown_object.content, DynType::Integer(2));
+        let cause = err.previous_exception.unwrap();
+        assert_eq!(*cause.thrown_object.content, DynType::Integer(1));
+    }
+
+    #[test]
+    fn recursion_through_try_keeps_the_stack_flat() {
+        let code = "
+            (def (depth n) (if (= n 0) (throw 0) (try (+ 1 (depth (- n 1))) (finally 0))))
+            (def (sum n) (if (= n 0) 0 (try (+ 1 (sum (- n 1))) (catch e e))))
+            (+ (sum 100000) (try (depth 100000) (catch e 7)))";
+        assert_eq!(integer(code), 100_007);
+    }
+
+    #[test]
+    fn traceback_has_every_call() {
+        let err = fail("(def (f) (throw 1))\n(def (g) (try (f) (finally 0)))\n(g)");
+        let lines: Vec<_> = err
+            .traceback
+            .iter()
+            .map(|frame| frame.position.unwrap().0)
+            .collect();
+        assert_eq!(lines, [1, 2, 3]);
+    }
+
+    #[test]
+    fn undefined_variables_fail_before_running() {
+        let mut interpreter = Interpreter::new();
+        match interpreter.eval_str("(let a 1)\n(do (set a 2) (set b 2))") {
+            Err(Error::Undefined(errors)) => assert_eq!(
+                *errors[0].thrown_object.content,
+                DynType::Str("variable b is undefined and cannot be set".to_string())
+            ),
+            _ => panic!("expected an undefined variable"),
+        }
+        assert_eq!(
+            *interpreter.eval_str("a").unwrap().content,
+            DynType::Integer(1)
+        );
+    }
+
+    #[test]
+    fn functions_use_later_definitions() {
+        let code = "(def (f) (g))\n(def (g) 1)\n(f)";
+        assert_eq!(*eval(code).content, DynType::Integer(1));
+    }
+
+    #[test]
+    fn macros_call_functions_defined_above() {
+        let code = "(def (helper x) (list (symbol \"+\") x 1))\n(defmacro (m x) (helper x))\n(m 1)";
+        assert_eq!(*eval(code).content, DynType::Integer(2));
+    }
+
+    #[test]
+    fn builtins_are_shadowed_but_not_set() {
+        let err = fail("(set + -)");
+        assert_eq!(
+            *err.thrown_object.content,
+            DynType::Str("built-in + cannot be set".to_string())
+        );
+        assert_eq!(integer("(let + -)\n(+ 5 3)"), 2);
+    }
+}
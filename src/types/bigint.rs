@@ -0,0 +1,537 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    ops::{Add, Mul, Neg, Sub},
+};
+
+// Integer of any size. The magnitude is kept by 32-bit digits from the lowest one,
+// there are no leading zero digits, so zero has no digits at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+const BASE: u64 = 1 << 32;
+
+fn trim(magnitude: &mut Vec<u32>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+fn compare_magnitudes(x: &[u32], y: &[u32]) -> Ordering {
+    x.len()
+        .cmp(&y.len())
+        .then_with(|| x.iter().rev().cmp(y.iter().rev()))
+}
+
+fn add_magnitudes(x: &[u32], y: &[u32]) -> Vec<u32> {
+    let (long, short) = if x.len() >= y.len() { (x, y) } else { (y, x) };
+    let mut result = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0;
+    for (index, digit) in long.iter().enumerate() {
+        let sum = *digit as u64 + *short.get(index).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+// the first magnitude must not be less than the second one
+fn sub_magnitudes(x: &[u32], y: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(x.len());
+    let mut borrow = 0;
+    for (index, digit) in x.iter().enumerate() {
+        let difference = *digit as i64 - *y.get(index).unwrap_or(&0) as i64 - borrow;
+        result.push(difference as u32);
+        borrow = if difference < 0 { 1 } else { 0 };
+    }
+    trim(&mut result);
+    result
+}
+
+fn mul_magnitudes(x: &[u32], y: &[u32]) -> Vec<u32> {
+    if x.is_empty() || y.is_empty() {
+        return vec![];
+    }
+    let mut result = vec![0u32; x.len() + y.len()];
+    for (i, x_digit) in x.iter().enumerate() {
+        let mut carry = 0;
+        for (j, y_digit) in y.iter().enumerate() {
+            let product = *x_digit as u64 * *y_digit as u64 + result[i + j] as u64 + carry;
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        result[i + y.len()] = carry as u32;
+    }
+    trim(&mut result);
+    result
+}
+
+// magnitude * factor + addend
+fn mul_add_digit(magnitude: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = addend as u64;
+    for digit in magnitude.iter_mut() {
+        let product = *digit as u64 * factor as u64 + carry;
+        *digit = product as u32;
+        carry = product >> 32;
+    }
+    if carry > 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+// returns the remainder, the magnitude becomes the quotient
+fn div_digit(magnitude: &mut Vec<u32>, divisor: u32) -> u32 {
+    let mut remainder = 0;
+    for digit in magnitude.iter_mut().rev() {
+        let current = (remainder << 32) | *digit as u64;
+        *digit = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+    trim(magnitude);
+    remainder as u32
+}
+
+fn shift_left(magnitude: &[u32], bits: u32) -> Vec<u32> {
+    if bits == 0 {
+        return magnitude.to_vec();
+    }
+    let mut result = Vec::with_capacity(magnitude.len() + 1);
+    let mut carry = 0;
+    for digit in magnitude {
+        result.push((digit << bits) | carry);
+        carry = digit >> (32 - bits);
+    }
+    result.push(carry);
+    result
+}
+
+// Long division by the algorithm D of Knuth, the divisor has at least two digits
+fn div_rem_magnitudes(dividend: &[u32], divisor: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    // the highest digit of the divisor gets the highest bit, so the guessed digits of the quotient are close
+    let shift = divisor.last().unwrap().leading_zeros();
+    let mut divisor = shift_left(divisor, shift);
+    trim(&mut divisor);
+    let mut remainder = shift_left(dividend, shift);
+    if remainder.len() == dividend.len() {
+        remainder.push(0);
+    }
+
+    let n = divisor.len();
+    let m = dividend.len() - n;
+    let mut quotient = vec![0u32; m + 1];
+    for j in (0..=m).rev() {
+        let top = ((remainder[j + n] as u64) << 32) | remainder[j + n - 1] as u64;
+        let mut guess = top / divisor[n - 1] as u64;
+        let mut rest = top % divisor[n - 1] as u64;
+        while guess >= BASE
+            || guess as u128 * divisor[n - 2] as u128
+                > ((rest as u128) << 32) | remainder[j + n - 2] as u128
+        {
+            guess -= 1;
+            rest += divisor[n - 1] as u64;
+            if rest >= BASE {
+                break;
+            }
+        }
+
+        // subtracts guess * divisor from the current part of the remainder
+        let mut borrow = 0i128;
+        let mut carry = 0u128;
+        for i in 0..n {
+            let product = guess as u128 * divisor[i] as u128 + carry;
+            carry = product >> 32;
+            let difference = remainder[i + j] as i128 - borrow - (product & 0xffff_ffff) as i128;
+            remainder[i + j] = difference as u32;
+            borrow = if difference < 0 { 1 } else { 0 };
+        }
+        let difference = remainder[j + n] as i128 - borrow - carry as i128;
+        remainder[j + n] = difference as u32;
+
+        // the guess was too big by one, the divisor is added back
+        if difference < 0 {
+            guess -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let sum = remainder[i + j] as u64 + divisor[i] as u64 + carry;
+                remainder[i + j] = sum as u32;
+                carry = sum >> 32;
+            }
+            remainder[j + n] = (remainder[j + n] as u64 + carry) as u32;
+        }
+        quotient[j] = guess as u32;
+    }
+
+    remainder.truncate(n);
+    let mut remainder = if shift == 0 {
+        remainder
+    } else {
+        let mut shifted = Vec::with_capacity(n);
+        for i in 0..n {
+            let high = remainder
+                .get(i + 1)
+                .map_or(0, |digit| digit << (32 - shift));
+            shifted.push((remainder[i] >> shift) | high);
+        }
+        shifted
+    };
+    trim(&mut quotient);
+    trim(&mut remainder);
+    (quotient, remainder)
+}
+
+impl BigInt {
+    fn new(negative: bool, mut magnitude: Vec<u32>) -> BigInt {
+        trim(&mut magnitude);
+        BigInt {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    pub fn zero() -> BigInt {
+        BigInt::new(false, vec![])
+    }
+
+    pub fn one() -> BigInt {
+        BigInt::from(1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn abs(&self) -> BigInt {
+        BigInt::new(false, self.magnitude.clone())
+    }
+
+    // digits of the radix without a sign
+    pub fn parse(digits: &str, radix: u32) -> Option<BigInt> {
+        if digits.is_empty() {
+            return None;
+        }
+        let mut magnitude = vec![];
+        for ch in digits.chars() {
+            mul_add_digit(&mut magnitude, radix, ch.to_digit(radix)?);
+        }
+        Some(BigInt::new(false, magnitude))
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+        let mut magnitude = 0u64;
+        for digit in self.magnitude.iter().rev() {
+            magnitude = (magnitude << 32) | *digit as u64;
+        }
+        if self.negative {
+            if magnitude <= i64::MAX as u64 + 1 {
+                Some((magnitude as i64).wrapping_neg())
+            } else {
+                None
+            }
+        } else {
+            if magnitude <= i64::MAX as u64 {
+                Some(magnitude as i64)
+            } else {
+                None
+            }
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0.0, |accum, digit| accum * BASE as f64 + *digit as f64);
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    // count of the significant bits of the magnitude
+    pub fn bits(&self) -> u64 {
+        match self.magnitude.last() {
+            Some(digit) => self.magnitude.len() as u64 * 32 - digit.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    // multiplies by 2^bits
+    pub fn shift_left(&self, bits: u32) -> BigInt {
+        let mut magnitude = vec![0u32; (bits / 32) as usize];
+        magnitude.extend(shift_left(&self.magnitude, bits % 32));
+        BigInt::new(self.negative, magnitude)
+    }
+
+    // the quotient is rounded toward zero, the remainder has the sign of the dividend
+    pub fn div_rem(&self, divisor: &BigInt) -> (BigInt, BigInt) {
+        let (quotient, remainder) = match divisor.magnitude.len() {
+            0 => panic!("division of a big integer by zero"),
+            _ if compare_magnitudes(&self.magnitude, &divisor.magnitude) == Ordering::Less => {
+                (vec![], self.magnitude.clone())
+            }
+            1 => {
+                let mut quotient = self.magnitude.clone();
+                let remainder = div_digit(&mut quotient, divisor.magnitude[0]);
+                (quotient, vec![remainder])
+            }
+            _ => div_rem_magnitudes(&self.magnitude, &divisor.magnitude),
+        };
+        (
+            BigInt::new(self.negative != divisor.negative, quotient),
+            BigInt::new(self.negative, remainder),
+        )
+    }
+
+    // the quotient is rounded down
+    pub fn div_floor(&self, divisor: &BigInt) -> BigInt {
+        let (quotient, remainder) = self.div_rem(divisor);
+        if !remainder.is_zero() && remainder.negative != divisor.negative {
+            &quotient - &BigInt::one()
+        } else {
+            quotient
+        }
+    }
+
+    // the remainder has the sign of the divisor
+    pub fn mod_floor(&self, divisor: &BigInt) -> BigInt {
+        let (_, remainder) = self.div_rem(divisor);
+        if !remainder.is_zero() && remainder.negative != divisor.negative {
+            &remainder + divisor
+        } else {
+            remainder
+        }
+    }
+
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let mut x = self.abs();
+        let mut y = other.abs();
+        while !y.is_zero() {
+            let (_, remainder) = x.div_rem(&y);
+            x = y;
+            y = remainder;
+        }
+        x
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(number: i64) -> Self {
+        let magnitude = number.unsigned_abs();
+        BigInt::new(number < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(
+                self.negative,
+                add_magnitudes(&self.magnitude, &other.magnitude),
+            );
+        }
+        match compare_magnitudes(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::new(
+                other.negative,
+                sub_magnitudes(&other.magnitude, &self.magnitude),
+            ),
+            _ => BigInt::new(
+                self.negative,
+                sub_magnitudes(&self.magnitude, &other.magnitude),
+            ),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::new(
+            self.negative != other.negative,
+            mul_magnitudes(&self.magnitude, &other.magnitude),
+        )
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.magnitude, &other.magnitude),
+            (true, true) => compare_magnitudes(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // groups of 9 decimal digits from the lowest one
+        let mut magnitude = self.magnitude.clone();
+        let mut groups = vec![];
+        while !magnitude.is_empty() {
+            groups.push(div_digit(&mut magnitude, 1_000_000_000));
+        }
+        let mut string = String::new();
+        if self.negative {
+            string.push('-');
+        }
+        string.push_str(&groups.pop().unwrap().to_string());
+        for group in groups.iter().rev() {
+            string.push_str(&format!("{:09}", group));
+        }
+        write!(f, "{}", string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(text: &str) -> BigInt {
+        match text.strip_prefix('-') {
+            Some(digits) => -&BigInt::parse(digits, 10).unwrap(),
+            None => BigInt::parse(text, 10).unwrap(),
+        }
+    }
+
+    fn from_i128(number: i128) -> BigInt {
+        big(&number.to_string())
+    }
+
+    // numbers of up to 127 bits with every sign, so the divisors have one to four limbs
+    fn samples() -> Vec<i128> {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut samples = vec![1, -1, 7, u32::MAX as i128, 1 << 32, (1 << 64) - 1, 1 << 64];
+        for bits in [20, 40, 70, 100, 126] {
+            for _ in 0..6 {
+                let number = ((next() as u128) << 64 | next() as u128) >> (128 - bits);
+                let number = number as i128 | 1;
+                samples.push(if next() % 2 == 0 { number } else { -number });
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn division_agrees_with_i128() {
+        let samples = samples();
+        for x in &samples {
+            for y in &samples {
+                let (quotient, remainder) = from_i128(*x).div_rem(&from_i128(*y));
+                assert_eq!(quotient, from_i128(x / y), "{} / {}", x, y);
+                assert_eq!(remainder, from_i128(x % y), "{} % {}", x, y);
+                let floor = if x % y != 0 && (*x < 0) != (*y < 0) {
+                    x / y - 1
+                } else {
+                    x / y
+                };
+                assert_eq!(
+                    from_i128(*x).div_floor(&from_i128(*y)),
+                    from_i128(floor),
+                    "{} div {}",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn arithmetic_agrees_with_i128() {
+        let samples: Vec<_> = samples().into_iter().map(|x| x >> 64).collect();
+        for x in &samples {
+            for y in &samples {
+                let (big_x, big_y) = (from_i128(*x), from_i128(*y));
+                assert_eq!(&big_x + &big_y, from_i128(x + y));
+                assert_eq!(&big_x - &big_y, from_i128(x - y));
+                assert_eq!(&big_x * &big_y, from_i128(x * y));
+                assert_eq!(big_x.cmp(&big_y), x.cmp(y));
+            }
+        }
+    }
+
+    #[test]
+    fn long_division() {
+        let x = big("123456789012345678901234567890123456789012345678901234567890");
+        let y = big("98765432109876543210987654321");
+        let remainder = big("1234567890123456789");
+        let (quotient, rest) = (&(&x * &y) + &remainder).div_rem(&y);
+        assert_eq!((quotient, rest), (x.clone(), remainder.clone()));
+
+        // the guessed digits of the quotient are one too big, so the divisor is added back
+        let limbs = |magnitude: &[u32]| BigInt::new(false, magnitude.to_vec());
+        let (quotient, rest) = limbs(&[3, 0, 0x8000_0000]).div_rem(&limbs(&[1, 0, 0x2000_0000]));
+        assert_eq!((quotient, rest), (big("3"), limbs(&[0, 0, 0x2000_0000])));
+        let dividend = limbs(&[0, 0, 0x8000, 0x7fff]);
+        let divisor = limbs(&[1, 0, 0x8000]);
+        let (quotient, rest) = dividend.div_rem(&divisor);
+        assert_eq!(quotient, limbs(&[0xfffe_0000]));
+        assert_eq!(&(&quotient * &divisor) + &rest, dividend);
+        assert!(rest < divisor);
+
+        let y = -&y;
+        assert_eq!(x.mod_floor(&y), &x - &(&x.div_floor(&y) * &y));
+        assert!(x.mod_floor(&y).is_negative() && x.mod_floor(&y) > y);
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(BigInt::from(i64::MIN).to_string(), "-9223372036854775808");
+        assert_eq!(BigInt::from(i64::MIN).to_i64(), Some(i64::MIN));
+        assert_eq!(big("9223372036854775808").to_i64(), None);
+        assert_eq!(BigInt::parse("ff_ff", 16), None);
+        assert_eq!(BigInt::parse("ffff", 16), Some(BigInt::from(0xffff)));
+        assert_eq!(big("-0"), BigInt::zero());
+        assert_eq!(big("18446744073709551616").to_f64(), 18446744073709551616.0);
+        assert_eq!(big("12").gcd(&big("-18")), big("6"));
+    }
+}
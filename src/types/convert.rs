@@ -0,0 +1,316 @@
+use std::{collections::HashMap, hash::Hash, rc::Rc};
+
+use super::{
+    exception::Exception,
+    list::{list_of, List, ListItem},
+    map::{Map, MapKey},
+    value::Value,
+    ClosureRef, DynType,
+};
+
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, Exception>;
+}
+
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self, Exception> {
+        Ok(value.clone())
+    }
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::new(DynType::Nil, None)
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: &Value) -> Result<Self, Exception> {
+        value.content.to_number()
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> Value {
+        Value::new(DynType::Number(self), None)
+    }
+}
+
+impl FromValue for i64 {
+    fn from_value(value: &Value) -> Result<Self, Exception> {
+        value.content.to_integer()
+    }
+}
+
+impl IntoValue for i64 {
+    fn into_value(self) -> Value {
+        Value::new(DynType::Integer(self), None)
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, Exception> {
+        if let DynType::Str(string) = &*value.content {
+            Ok(string.clone())
+        } else {
+            Err(Exception::from(format!(
+                "Expected Str, given {}",
+                value.content
+            )))
+        }
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::new(DynType::Str(self), None)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::new(DynType::Str(self.to_string()), None)
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self, Exception> {
+        if let DynType::Bool(boolean) = &*value.content {
+            Ok(*boolean)
+        } else {
+            Err(Exception::from(format!(
+                "Expected Bool, given {}",
+                value.content
+            )))
+        }
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Value::new(DynType::Bool(self), None)
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self, Exception> {
+        match &*value.content {
+            DynType::Nil => Ok(None),
+            _ => Ok(Some(T::from_value(value)?)),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        match self {
+            Some(value) => value.into_value(),
+            None => Value::new(DynType::Nil, None),
+        }
+    }
+}
+
+fn list_items(value: &Value) -> Result<Vec<Value>, Exception> {
+    let mut list = List::new(value.clone());
+    let mut items = vec![];
+    while let ListItem::Middle(item) = list.next() {
+        items.push(item);
+    }
+    list.next().to_end()?;
+    Ok(items)
+}
+
+// vector or proper linked list, it goes back as a linked list
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Result<Self, Exception> {
+        match &*value.content {
+            DynType::Vector(items) => items.iter().map(T::from_value).collect(),
+            DynType::Pair(_) | DynType::Nil => {
+                list_items(value)?.iter().map(T::from_value).collect()
+            }
+            other => Err(Exception::from(format!(
+                "Expected List or Vector, given {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        list_of(
+            self.into_iter().map(IntoValue::into_value).collect(),
+            Value::new(DynType::Nil, None),
+        )
+    }
+}
+
+// map of YAL, the keys are converted by their values
+impl<K: FromValue + Eq + Hash, V: FromValue> FromValue for HashMap<K, V> {
+    fn from_value(value: &Value) -> Result<Self, Exception> {
+        value
+            .content
+            .to_map()?
+            .entries()
+            .map(|(key, value)| Ok((K::from_value(&key.to_value())?, V::from_value(value)?)))
+            .collect()
+    }
+}
+
+impl<K: Into<MapKey>, V: IntoValue> IntoValue for HashMap<K, V> {
+    fn into_value(self) -> Value {
+        let entries = self
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into_value()))
+            .collect();
+        Value::new(DynType::Map(Map::new(entries)), None)
+    }
+}
+
+fn arguments_count_error(expected: usize, given: usize) -> Exception {
+    Exception::from(format!(
+        "Arguments count error, expected {}, given {}",
+        expected, given
+    ))
+}
+
+// tuples are lists of fixed length
+macro_rules! impl_tuple {
+    ($count:expr; $($item:ident),+) => {
+        impl<$($item: FromValue),+> FromValue for ($($item,)+) {
+            fn from_value(value: &Value) -> Result<Self, Exception> {
+                let items = list_items(value)?;
+                if items.len() != $count {
+                    return Err(Exception::from(format!(
+                        "Expected list of {} items, given {}",
+                        $count,
+                        items.len()
+                    )));
+                }
+                let mut items = items.iter();
+                Ok(($($item::from_value(items.next().unwrap())?,)+))
+            }
+        }
+
+        impl<$($item: IntoValue),+> IntoValue for ($($item,)+) {
+            #[allow(non_snake_case)]
+            fn into_value(self) -> Value {
+                let ($($item,)+) = self;
+                list_of(vec![$($item.into_value()),+], Value::new(DynType::Nil, None))
+            }
+        }
+    };
+}
+
+impl_tuple!(1; A);
+impl_tuple!(2; A, B);
+impl_tuple!(3; A, B, C);
+impl_tuple!(4; A, B, C, D);
+
+// Wraps a typed Rust function into a closure which checks the count and types of its arguments
+pub trait IntoClosure<Args> {
+    fn into_closure(self) -> ClosureRef;
+}
+
+macro_rules! impl_into_closure {
+    ($count:expr; $($arg:ident),*) => {
+        impl<F, R, E, $($arg),*> IntoClosure<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> Result<R, E> + 'static,
+            R: IntoValue,
+            E: Into<Exception>,
+            $($arg: FromValue,)*
+        {
+            fn into_closure(self) -> ClosureRef {
+                Rc::new(move |args| {
+                    let items = list_items(&args)?;
+                    if items.len() != $count {
+                        return Err(arguments_count_error($count, items.len()));
+                    }
+                    #[allow(unused_mut, unused_variables)]
+                    let mut items = items.iter();
+                    match self($($arg::from_value(items.next().unwrap())?),*) {
+                        Ok(result) => Ok(result.into_value()),
+                        Err(err) => Err(err.into()),
+                    }
+                })
+            }
+        }
+    };
+}
+
+impl_into_closure!(0;);
+impl_into_closure!(1; A1);
+impl_into_closure!(2; A1, A2);
+impl_into_closure!(3; A1, A2, A3);
+impl_into_closure!(4; A1, A2, A3, A4);
+impl_into_closure!(5; A1, A2, A3, A4, A5);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(content: DynType) -> Value {
+        Value::new(content, None)
+    }
+
+    fn message(err: Exception) -> String {
+        err.thrown_object.content.to_string()
+    }
+
+    #[test]
+    fn bool_is_only_bool() {
+        assert!(bool::from_value(&value(DynType::Bool(true))).unwrap());
+        assert!(!bool::from_value(&value(DynType::Bool(false))).unwrap());
+        let err = bool::from_value(&value(DynType::Integer(1))).unwrap_err();
+        assert_eq!(message(err), "Expected Bool, given 1");
+        assert!(bool::from_value(&value(DynType::Nil)).is_err());
+        assert_eq!(*true.into_value().content, DynType::Bool(true));
+    }
+
+    #[test]
+    fn hash_map_is_map() {
+        let mut map = HashMap::new();
+        map.insert("one".to_string(), 1i64);
+        map.insert("two".to_string(), 2);
+        let converted = map.clone().into_value();
+        let entries = converted.content.to_map().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            *entries.get(&"two".into()).unwrap().content,
+            DynType::Integer(2)
+        );
+        assert_eq!(HashMap::<String, i64>::from_value(&converted).unwrap(), map);
+
+        let alist = vec![("one".to_string(), 1i64)].into_value();
+        let err = HashMap::<String, i64>::from_value(&alist).unwrap_err();
+        assert!(message(err).starts_with("Expected Map, given"));
+    }
+
+    #[test]
+    fn vec_is_list_or_vector() {
+        let numbers = |items: Vec<i64>| items.into_iter().map(IntoValue::into_value).collect();
+        let list = vec![1i64, 2, 3].into_value();
+        let vector = value(DynType::Vector(Rc::new(numbers(vec![1, 2, 3]))));
+        assert_eq!(Vec::<i64>::from_value(&list).unwrap(), [1, 2, 3]);
+        assert_eq!(Vec::<i64>::from_value(&vector).unwrap(), [1, 2, 3]);
+        assert!(Vec::<i64>::from_value(&value(DynType::Nil))
+            .unwrap()
+            .is_empty());
+
+        let err = Vec::<i64>::from_value(&value(DynType::Integer(1))).unwrap_err();
+        assert_eq!(message(err), "Expected List or Vector, given 1");
+        let err = Vec::<String>::from_value(&vector).unwrap_err();
+        assert_eq!(message(err), "Expected Str, given 1");
+    }
+}
@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use crate::types::list::{List, ListItem};
 
-use super::value::Value;
+use super::{show, value::Value};
 
 #[derive(Debug, Clone)]
 pub struct DotPair {
@@ -12,14 +12,17 @@ pub struct DotPair {
 
 impl Display for DotPair {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let alternate = f.alternate();
         let mut buffer = String::new();
-        buffer.push_str(format!("({}", self.left.content).as_str());
+        buffer.push_str(format!("({}", show(&self.left.content, alternate)).as_str());
         let mut list = List::new(self.right.clone());
         while let ListItem::Middle(value) = list.next() {
-            buffer.push_str(format!(" {}", value.content).as_str());
+            buffer.push_str(format!(" {}", show(&value.content, alternate)).as_str());
         }
         match list.next() {
-            ListItem::Last(v) => buffer.push_str(format!(" . {})", v.content).as_str()),
+            ListItem::Last(v) => {
+                buffer.push_str(format!(" . {})", show(&v.content, alternate)).as_str())
+            }
             ListItem::End => buffer.push(')'),
             ListItem::Middle(_) => unreachable!(),
         }
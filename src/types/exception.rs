@@ -1,8 +1,48 @@
-use super::value::Value;
+use std::{path::PathBuf, rc::Rc};
+
+use super::{value::Value, DynType};
+
+// place in the source where an exception has passed through
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub file: Option<Rc<PathBuf>>,
+    pub position: Option<(u32, u32)>,
+}
 
 #[derive(Debug)]
 pub struct Exception {
     pub thrown_object: Value,
-    pub traceback: Vec<Option<(u32, u16)>>,
+    pub traceback: Vec<Frame>,
     pub previous_exception: Option<Box<Exception>>,
 }
+
+impl Exception {
+    pub fn new(thrown_object: Value) -> Self {
+        Self {
+            thrown_object,
+            traceback: vec![],
+            previous_exception: None,
+        }
+    }
+
+    // appends the cause to the end of the exception chain
+    pub fn caused_by(mut self, cause: Exception) -> Self {
+        self.previous_exception = Some(Box::new(match self.previous_exception.take() {
+            Some(previous) => previous.caused_by(cause),
+            None => cause,
+        }));
+        self
+    }
+}
+
+impl From<String> for Exception {
+    fn from(message: String) -> Self {
+        Exception::new(Value::new(DynType::Str(message), None))
+    }
+}
+
+impl From<&str> for Exception {
+    fn from(message: &str) -> Self {
+        Exception::from(message.to_string())
+    }
+}
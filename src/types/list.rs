@@ -1,4 +1,4 @@
-use super::{exception::Exception, value::Value, DynType};
+use super::{dot_pair::DotPair, exception::Exception, value::Value, DynType};
 
 pub enum ListItem {
     Middle(Value),
@@ -6,6 +6,7 @@ pub enum ListItem {
     End,
 }
 
+#[allow(clippy::wrong_self_convention)]
 impl ListItem {
     pub fn to_middle(self) -> Result<Value, Exception> {
         match self {
@@ -22,7 +23,7 @@ impl ListItem {
                 previous_exception: None,
             }),
             ListItem::End => Err(Exception {
-                thrown_object: Value::new(DynType::Str(format!("Unexpected end of list")), None),
+                thrown_object: Value::new(DynType::Str("Unexpected end of list".to_string()), None),
                 traceback: vec![],
                 previous_exception: None,
             }),
@@ -44,6 +45,21 @@ impl ListItem {
     }
 }
 
+// linked list of the items which ends with the tail, nil makes a proper list
+pub fn list_of(items: Vec<Value>, tail: Value) -> Value {
+    let mut list = tail;
+    for item in items.into_iter().rev() {
+        list = Value::new(
+            DynType::Pair(DotPair {
+                left: item,
+                right: list,
+            }),
+            None,
+        );
+    }
+    list
+}
+
 pub struct List {
     pub current_value: Value,
 }
@@ -55,6 +71,7 @@ impl List {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> ListItem {
         let current_value = self.current_value.clone();
         match &*current_value.content {
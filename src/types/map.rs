@@ -0,0 +1,261 @@
+use std::{cmp::Ordering, collections::BTreeMap, fmt::Display};
+
+use super::{
+    bigint::BigInt, exception::Exception, number, quote_str, rational::Rational, symbol::Symbol,
+    value::Value, DynType,
+};
+
+// Numbers, strings and symbols could be keys. Keys are sorted, so maps are printed in the same order.
+#[derive(Debug, Clone)]
+pub enum MapKey {
+    Integer(i64),
+    BigInteger(BigInt),
+    Rational(Rational),
+    Number(f64),
+    Str(String),
+    Symbol(Symbol),
+}
+
+impl MapKey {
+    pub fn from_value(value: &Value) -> Result<MapKey, Exception> {
+        match &*value.content {
+            DynType::Integer(number) => Ok(MapKey::Integer(*number)),
+            DynType::BigInteger(number) => Ok(MapKey::BigInteger(number.clone())),
+            DynType::Rational(number) => Ok(MapKey::Rational(number.clone())),
+            DynType::Number(number) => Ok(MapKey::Number(*number)),
+            DynType::Str(string) => Ok(MapKey::Str(string.clone())),
+            DynType::Symbol(symbol) => Ok(MapKey::Symbol(*symbol)),
+            other => Err(Exception::from(format!(
+                "{} cannot be a key of a map",
+                other
+            ))),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        let content = match self {
+            MapKey::Integer(number) => DynType::Integer(*number),
+            MapKey::BigInteger(number) => DynType::BigInteger(number.clone()),
+            MapKey::Rational(number) => DynType::Rational(number.clone()),
+            MapKey::Number(number) => DynType::Number(*number),
+            MapKey::Str(string) => DynType::Str(string.clone()),
+            MapKey::Symbol(symbol) => DynType::Symbol(*symbol),
+        };
+        Value::new(content, None)
+    }
+
+    fn order_value(&self) -> u8 {
+        match self {
+            MapKey::Integer(_) | MapKey::BigInteger(_) | MapKey::Rational(_) => 1,
+            MapKey::Number(_) => 2,
+            MapKey::Str(_) => 3,
+            MapKey::Symbol(_) => 4,
+        }
+    }
+}
+
+// keys of Rust maps which are converted to YAL maps
+impl From<i64> for MapKey {
+    fn from(number: i64) -> Self {
+        MapKey::Integer(number)
+    }
+}
+
+impl From<f64> for MapKey {
+    fn from(number: f64) -> Self {
+        MapKey::Number(number)
+    }
+}
+
+impl From<String> for MapKey {
+    fn from(string: String) -> Self {
+        MapKey::Str(string)
+    }
+}
+
+impl From<&str> for MapKey {
+    fn from(string: &str) -> Self {
+        MapKey::Str(string.to_string())
+    }
+}
+
+impl From<Symbol> for MapKey {
+    fn from(symbol: Symbol) -> Self {
+        MapKey::Symbol(symbol)
+    }
+}
+
+impl Display for MapKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapKey::Str(string) if f.alternate() => write!(f, "{}", quote_str(string)),
+            MapKey::Integer(number) => write!(f, "{}", number),
+            MapKey::BigInteger(number) => write!(f, "{}", number),
+            MapKey::Rational(number) => write!(f, "{}", number),
+            MapKey::Number(number) => write!(f, "{}", number::float_to_string(*number)),
+            MapKey::Str(string) => write!(f, "{}", string),
+            MapKey::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+fn nan_order(nan: f64) -> Ordering {
+    if nan.is_sign_negative() {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
+
+impl Ord for MapKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            // 0 and -0 are the same key
+            (MapKey::Number(x), MapKey::Number(y)) => (x + 0.0).total_cmp(&(y + 0.0)),
+            // NaN isn't comparable to the exact numbers, it goes after all of them
+            // or before them with the sign, like in total_cmp
+            (MapKey::Number(x), _) if x.is_nan() && other.order_value() == 1 => nan_order(*x),
+            (_, MapKey::Number(y)) if y.is_nan() && self.order_value() == 1 => {
+                nan_order(*y).reverse()
+            }
+            // 1 and 1.0 are different keys, but numbers are sorted by their values
+            (
+                MapKey::Integer(_)
+                | MapKey::BigInteger(_)
+                | MapKey::Rational(_)
+                | MapKey::Number(_),
+                MapKey::Integer(_)
+                | MapKey::BigInteger(_)
+                | MapKey::Rational(_)
+                | MapKey::Number(_),
+            ) => number::compare(&self.to_value().content, &other.to_value().content)
+                .unwrap_or(Ordering::Equal)
+                .then(self.order_value().cmp(&other.order_value())),
+            (MapKey::Str(x), MapKey::Str(y)) => x.cmp(y),
+            (MapKey::Symbol(x), MapKey::Symbol(y)) => x.name().cmp(&y.name()),
+            _ => self.order_value().cmp(&other.order_value()),
+        }
+    }
+}
+
+impl PartialOrd for MapKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MapKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MapKey {}
+
+// Maps are never changed, `assoc` and `dissoc` make new ones
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    entries: BTreeMap<MapKey, Value>,
+}
+
+impl Map {
+    pub fn new(entries: BTreeMap<MapKey, Value>) -> Self {
+        Self { entries }
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<&Value> {
+        self.entries.get(key)
+    }
+
+    pub fn contains(&self, key: &MapKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn assoc(&self, key: MapKey, value: Value) -> Map {
+        let mut entries = self.entries.clone();
+        entries.insert(key, value);
+        Map { entries }
+    }
+
+    pub fn dissoc(&self, key: &MapKey) -> Map {
+        let mut entries = self.entries.clone();
+        entries.remove(key);
+        Map { entries }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&MapKey, &Value)> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Display for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(key, value)| {
+                if f.alternate() {
+                    format!("{:#} {:#}", key, value.content)
+                } else {
+                    format!("{} {}", key, value.content)
+                }
+            })
+            .collect();
+        write!(f, "{{{}}}", entries.join(" "))
+    }
+}
+
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries.len() == other.entries.len()
+            && self.entries.iter().zip(other.entries.iter()).all(
+                |((key1, value1), (key2, value2))| key1 == key2 && value1.content == value2.content,
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn order_of_keys_is_total() {
+        let keys = [
+            MapKey::Number(f64::NAN),
+            MapKey::Number(-f64::NAN),
+            MapKey::Number(f64::INFINITY),
+            MapKey::Number(1.0),
+            MapKey::Number(-0.0),
+            MapKey::Integer(0),
+            MapKey::Integer(1),
+            MapKey::Integer(2),
+            MapKey::BigInteger(BigInt::from(i64::MIN)),
+            MapKey::Str("a".to_string()),
+        ];
+        for x in &keys {
+            for y in &keys {
+                assert_eq!(x.cmp(y), y.cmp(x).reverse());
+                for z in &keys {
+                    if x <= y && y <= z {
+                        assert!(x <= z, "{:?} {:?} {:?}", x, y, z);
+                    }
+                }
+            }
+        }
+
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+        let sorted: Vec<_> = sorted.iter().map(|key| format!("{:?}", key)).collect();
+        assert_eq!(sorted.first().unwrap(), "Number(NaN)");
+        assert_eq!(sorted[sorted.len() - 3], "Number(inf)");
+        assert_eq!(sorted[sorted.len() - 2], "Number(NaN)");
+    }
+}
@@ -1,38 +1,78 @@
+pub mod bigint;
+pub mod convert;
 pub mod dot_pair;
 pub mod exception;
 pub mod list;
-pub mod value;
+pub mod map;
+pub mod number;
+pub mod rational;
 pub mod struct_declare;
+pub mod symbol;
+pub mod value;
+
+use std::{
+    fmt::{Debug, Display},
+    rc::Rc,
+};
 
-use std::{fmt::{Debug, Display}, rc::Rc,};
+use crate::runtime::{custom_function::CustomFunction, macros::Macro};
 
 use self::{
+    bigint::BigInt,
     dot_pair::DotPair,
     exception::Exception,
-    value::Value,
+    map::Map,
+    rational::Rational,
     struct_declare::{Struct, StructType},
+    symbol::Symbol,
+    value::Value,
 };
 
+pub type ClosureRef = Rc<dyn Fn(Value) -> Result<Value, Exception>>;
+
 pub enum DynType {
     Nil,
+    Bool(bool),
+    Integer(i64),
+    BigInteger(BigInt),
+    Rational(Rational),
     Number(f64),
     Str(String),
-    Symbol(String),
+    Symbol(Symbol),
     Quoted(Value),
+    Quasiquoted(Value),
+    Unquoted(Value),
+    UnquoteSpliced(Value),
     Pair(DotPair),
-    Closure(Rc<dyn Fn(Value) -> Result<Value, Exception>>),
+    Map(Map),
+    Vector(Rc<Vec<Value>>),
+    Closure(ClosureRef),
+    Function(Rc<CustomFunction>),
+    Macro(Rc<Macro>),
     StructDeclare(Rc<StructType>),
     Struct(Struct),
 }
 
 impl DynType {
+    // only nil and false are false
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, DynType::Nil | DynType::Bool(false))
+    }
+
+    // exact numbers are converted to floats
     pub fn to_number(&self) -> Result<f64, Exception> {
         if let DynType::Number(num) = self {
             Ok(*num)
+        } else if let DynType::Integer(num) = self {
+            Ok(*num as f64)
+        } else if let DynType::BigInteger(num) = self {
+            Ok(num.to_f64())
+        } else if let DynType::Rational(num) = self {
+            Ok(num.to_f64())
         } else {
             Err(Exception {
                 thrown_object: Value::new(
-                    DynType::Str(format!("Expected Number, given, {}", &*self)),
+                    DynType::Str(format!("Expected Number, given, {}", self)),
                     None,
                 ),
                 traceback: vec![],
@@ -41,13 +81,28 @@ impl DynType {
         }
     }
 
-    pub fn to_symbol(&self) -> Result<String, Exception> {
-        if let DynType::Symbol(string) = self {
-            Ok(string.clone())
+    pub fn to_integer(&self) -> Result<i64, Exception> {
+        if let DynType::Integer(num) = self {
+            Ok(*num)
         } else {
             Err(Exception {
                 thrown_object: Value::new(
-                    DynType::Str(format!("Expected Number, given, {}", &*self)),
+                    DynType::Str(format!("Expected Integer, given, {}", self)),
+                    None,
+                ),
+                traceback: vec![],
+                previous_exception: None,
+            })
+        }
+    }
+
+    pub fn to_symbol(&self) -> Result<Symbol, Exception> {
+        if let DynType::Symbol(symbol) = self {
+            Ok(*symbol)
+        } else {
+            Err(Exception {
+                thrown_object: Value::new(
+                    DynType::Str(format!("Expected Symbol, given, {}", self)),
                     None,
                 ),
                 traceback: vec![],
@@ -62,7 +117,37 @@ impl DynType {
         } else {
             Err(Exception {
                 thrown_object: Value::new(
-                    DynType::Str(format!("Expected Pair, given, {}", &*self)),
+                    DynType::Str(format!("Expected Pair, given, {}", self)),
+                    None,
+                ),
+                traceback: vec![],
+                previous_exception: None,
+            })
+        }
+    }
+
+    pub fn to_map(&self) -> Result<&Map, Exception> {
+        if let DynType::Map(map) = self {
+            Ok(map)
+        } else {
+            Err(Exception {
+                thrown_object: Value::new(
+                    DynType::Str(format!("Expected Map, given, {}", self)),
+                    None,
+                ),
+                traceback: vec![],
+                previous_exception: None,
+            })
+        }
+    }
+
+    pub fn to_vector(&self) -> Result<Rc<Vec<Value>>, Exception> {
+        if let DynType::Vector(vector) = self {
+            Ok(vector.clone())
+        } else {
+            Err(Exception {
+                thrown_object: Value::new(
+                    DynType::Str(format!("Expected Vector, given, {}", self)),
                     None,
                 ),
                 traceback: vec![],
@@ -77,7 +162,7 @@ impl DynType {
         } else {
             Err(Exception {
                 thrown_object: Value::new(
-                    DynType::Str(format!("Expected Record, given, {}", &*self)),
+                    DynType::Str(format!("Expected Record, given, {}", self)),
                     None,
                 ),
                 traceback: vec![],
@@ -86,9 +171,12 @@ impl DynType {
         }
     }
 
-    pub fn to_closure(&self) -> Result<Rc<dyn Fn(Value) -> Result<Value, Exception>>, Exception> {
+    pub fn to_closure(&self) -> Result<ClosureRef, Exception> {
         if let DynType::Closure(closure) = self {
             Ok(closure.clone())
+        } else if let DynType::Function(function) = self {
+            let function = function.clone();
+            Ok(Rc::new(move |args| function.call(args)))
         } else {
             Err(Exception {
                 thrown_object: Value::new(
@@ -107,7 +195,7 @@ impl DynType {
         } else {
             Err(Exception {
                 thrown_object: Value::new(
-                    DynType::Str(format!("Expected RecordDeclare, given, {}", &*self)),
+                    DynType::Str(format!("Expected RecordDeclare, given, {}", self)),
                     None,
                 ),
                 traceback: vec![],
@@ -121,16 +209,26 @@ impl Debug for DynType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self {
             DynType::Nil => write!(f, "nil"),
-            DynType::Number(number) => write!(f, "Number({})", *number),
+            DynType::Bool(value) => write!(f, "Bool({})", value),
+            DynType::Integer(number) => write!(f, "Integer({})", *number),
+            DynType::BigInteger(number) => write!(f, "BigInteger({})", number),
+            DynType::Rational(number) => write!(f, "Rational({})", number),
+            DynType::Number(number) => write!(f, "Number({})", number::float_to_string(*number)),
             DynType::Str(string) => write!(f, "Str(\"{}\")", string),
             DynType::Symbol(symbol) => write!(f, "Symbol({})", symbol),
             DynType::Quoted(value) => write!(f, "Quoted({})", value.content),
+            DynType::Quasiquoted(value) => write!(f, "Quasiquoted({})", value.content),
+            DynType::Unquoted(value) => write!(f, "Unquoted({})", value.content),
+            DynType::UnquoteSpliced(value) => write!(f, "UnquoteSpliced({})", value.content),
             DynType::Pair(pair) => f
                 .debug_struct("Pair")
                 .field("left", &pair.left)
                 .field("right", &pair.right)
                 .finish(),
-            DynType::Closure(_) => write!(f, "<Closure>"),
+            DynType::Map(map) => f.debug_map().entries(map.entries()).finish(),
+            DynType::Vector(vector) => f.debug_list().entries(vector.iter()).finish(),
+            DynType::Closure(_) | DynType::Function(_) => write!(f, "<Closure>"),
+            DynType::Macro(_) => write!(f, "<Macro>"),
             DynType::StructDeclare(struct_declare) => f
                 .debug_struct("StructType")
                 .field("name", &struct_declare.name)
@@ -145,17 +243,68 @@ impl Debug for DynType {
     }
 }
 
+// Strings are written as literals with escapes, so the lexer reads them back the same
+pub fn quote_str(string: &str) -> String {
+    let mut quoted = String::from('"');
+    for ch in string.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            ch if ch.is_control() => quoted.push_str(&format!("\\u{{{:x}}}", ch as u32)),
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// the alternate form `{:#}` is kept for the nested values
+fn show(value: &DynType, alternate: bool) -> String {
+    if alternate {
+        format!("{:#}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+// `{}` prints strings as they are and `{:#}` prints them as literals, like `repr` does
 impl Display for DynType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let alternate = f.alternate();
         let string = match self {
             Self::Nil => String::from("nil"),
-            Self::Number(number) => number.to_string(),
+            Self::Bool(value) => value.to_string(),
+            Self::Integer(number) => number.to_string(),
+            Self::BigInteger(number) => number.to_string(),
+            Self::Rational(number) => number.to_string(),
+            Self::Number(number) => number::float_to_string(*number),
+            Self::Str(string) if alternate => quote_str(string),
             Self::Str(string) => string.clone(),
-            Self::Symbol(symbol) => symbol.clone(),
-            Self::Quoted(quoted) => format!("'{}", quoted.content),
+            Self::Symbol(symbol) => symbol.to_string(),
+            Self::Quoted(quoted) => format!("'{}", show(&quoted.content, alternate)),
+            Self::Quasiquoted(quoted) => format!("`{}", show(&quoted.content, alternate)),
+            Self::Unquoted(unquoted) => format!(",{}", show(&unquoted.content, alternate)),
+            Self::UnquoteSpliced(unquoted) => {
+                format!(",@{}", show(&unquoted.content, alternate))
+            }
+            Self::Pair(pair) if alternate => format!("{:#}", pair),
             Self::Pair(pair) => pair.to_string(),
-            Self::Closure(_) => String::from("<Closure>"),
+            Self::Map(map) if alternate => format!("{:#}", map),
+            Self::Map(map) => map.to_string(),
+            Self::Vector(vector) => {
+                let items: Vec<_> = vector
+                    .iter()
+                    .map(|item| show(&item.content, alternate))
+                    .collect();
+                format!("[{}]", items.join(" "))
+            }
+            Self::Closure(_) | Self::Function(_) => String::from("<Closure>"),
+            Self::Macro(_) => String::from("<Macro>"),
             Self::StructDeclare(struct_declare) => struct_declare.to_string(),
+            Self::Struct(struct_value) if alternate => format!("{:#}", struct_value),
             Self::Struct(struct_value) => struct_value.to_string(),
         };
         write!(f, "{}", string)
@@ -165,16 +314,39 @@ impl Display for DynType {
 impl PartialEq for DynType {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (DynType::Number(num1), DynType::Number(num2)) => *num1 == *num2,
+            (
+                DynType::Integer(_)
+                | DynType::BigInteger(_)
+                | DynType::Rational(_)
+                | DynType::Number(_),
+                DynType::Integer(_)
+                | DynType::BigInteger(_)
+                | DynType::Rational(_)
+                | DynType::Number(_),
+            ) => number::compare(self, other) == Some(std::cmp::Ordering::Equal),
             (DynType::Str(string1), DynType::Str(string2)) => *string1 == *string2,
-            (DynType::Quoted(value1), DynType::Quoted(value2)) => value1.content == value2.content,
+            (DynType::Quoted(value1), DynType::Quoted(value2))
+            | (DynType::Quasiquoted(value1), DynType::Quasiquoted(value2))
+            | (DynType::Unquoted(value1), DynType::Unquoted(value2))
+            | (DynType::UnquoteSpliced(value1), DynType::UnquoteSpliced(value2)) => {
+                value1.content == value2.content
+            }
             (DynType::Pair(pair1), DynType::Pair(pair2)) => {
                 pair1.left.content == pair2.left.content
                     && pair1.right.content == pair2.right.content
             }
+            (DynType::Map(map1), DynType::Map(map2)) => map1 == map2,
+            (DynType::Vector(vector1), DynType::Vector(vector2)) => {
+                vector1.len() == vector2.len()
+                    && vector1
+                        .iter()
+                        .zip(vector2.iter())
+                        .all(|(item1, item2)| item1.content == item2.content)
+            }
 
             (DynType::Nil, DynType::Nil) => true,
-            (DynType::Symbol(_), DynType::Symbol(_)) => false,
+            (DynType::Bool(value1), DynType::Bool(value2)) => value1 == value2,
+            (DynType::Symbol(symbol1), DynType::Symbol(symbol2)) => symbol1 == symbol2,
             (DynType::Closure(_), DynType::Closure(_)) => false,
             _ => false,
         }
@@ -184,9 +356,8 @@ impl PartialEq for DynType {
 impl PartialOrd for DynType {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         match (self, other) {
-            (DynType::Number(x), DynType::Number(y)) => x.partial_cmp(y),
             (DynType::Str(x), DynType::Str(y)) => x.partial_cmp(y),
-            _ => None,
+            _ => number::compare(self, other),
         }
     }
 }
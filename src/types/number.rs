@@ -0,0 +1,414 @@
+use std::cmp::Ordering;
+
+use super::{bigint::BigInt, exception::Exception, rational::Rational, DynType};
+
+// Operands of an arithmetic function. Both numbers are taken to the higher level of the tower:
+// integers, big integers, rationals and floats.
+enum Operands {
+    Integers(i64, i64),
+    BigIntegers(BigInt, BigInt),
+    Rationals(Rational, Rational),
+    Floats(f64, f64),
+}
+
+fn not_a_number(value: &DynType) -> Exception {
+    Exception::from(format!("Expected Number, given, {}", value))
+}
+
+fn level(value: &DynType) -> Result<u8, Exception> {
+    match value {
+        DynType::Integer(_) => Ok(0),
+        DynType::BigInteger(_) => Ok(1),
+        DynType::Rational(_) => Ok(2),
+        DynType::Number(_) => Ok(3),
+        other => Err(not_a_number(other)),
+    }
+}
+
+fn to_big_integer(value: &DynType) -> BigInt {
+    match value {
+        DynType::Integer(number) => BigInt::from(*number),
+        DynType::BigInteger(number) => number.clone(),
+        _ => unreachable!(),
+    }
+}
+
+fn to_rational(value: &DynType) -> Rational {
+    match value {
+        DynType::Rational(number) => number.clone(),
+        other => Rational::from_integer(to_big_integer(other)),
+    }
+}
+
+fn operands(x: &DynType, y: &DynType) -> Result<Operands, Exception> {
+    Ok(match level(x)?.max(level(y)?) {
+        0 => Operands::Integers(x.to_integer()?, y.to_integer()?),
+        1 => Operands::BigIntegers(to_big_integer(x), to_big_integer(y)),
+        2 => Operands::Rationals(to_rational(x), to_rational(y)),
+        _ => Operands::Floats(x.to_number()?, y.to_number()?),
+    })
+}
+
+// big integers which fit in i64 become usual ones
+pub fn integer(number: BigInt) -> DynType {
+    match number.to_i64() {
+        Some(number) => DynType::Integer(number),
+        None => DynType::BigInteger(number),
+    }
+}
+
+// rationals with the denominator 1 become integers
+pub fn rational(number: Rational) -> DynType {
+    if number.is_integer() {
+        integer(number.numerator().clone())
+    } else {
+        DynType::Rational(number)
+    }
+}
+
+// Digits of the radix, an underscore could be only between two digits like in `1_000_000`
+fn digits(text: &str, radix: u32) -> Option<String> {
+    if text.is_empty() {
+        return None;
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let is_digit = |index: Option<usize>| {
+        index
+            .and_then(|index| chars.get(index))
+            .is_some_and(|ch| ch.is_digit(radix))
+    };
+    for (index, ch) in chars.iter().enumerate() {
+        if *ch == '_' && !(is_digit(index.checked_sub(1)) && is_digit(Some(index + 1))) {
+            return None;
+        }
+        if *ch != '_' && !ch.is_digit(radix) {
+            return None;
+        }
+    }
+    Some(text.replace('_', ""))
+}
+
+fn parse_integer(text: &str, radix: u32) -> Option<BigInt> {
+    BigInt::parse(&digits(text, radix)?, radix)
+}
+
+// `1.5`, `1e9`, `2.5e-3`, there are digits before the dot
+fn parse_float(text: &str) -> Option<f64> {
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(index) => (&text[..index], Some(&text[index + 1..])),
+        None => (text, None),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let fraction = if fraction.is_empty() {
+        String::from("0")
+    } else {
+        digits(fraction, 10)?
+    };
+    let exponent = match exponent {
+        Some(exponent) => {
+            let (sign, exponent) = match exponent.strip_prefix('-') {
+                Some(exponent) => ("-", exponent),
+                None => ("", exponent.strip_prefix('+').unwrap_or(exponent)),
+            };
+            format!("{}{}", sign, digits(exponent, 10)?)
+        }
+        None => String::from("0"),
+    };
+    format!("{}.{}e{}", digits(whole, 10)?, fraction, exponent)
+        .parse()
+        .ok()
+}
+
+// Reads number literals: `42`, `-7`, `1_000_000`, `0xff`, `0o17`, `0b101`, `1/3`, `1.5`, `2.5e-3`,
+// `inf`, `-inf` and `nan`. Integers have no limit.
+pub fn parse(text: &str) -> Option<DynType> {
+    match text {
+        "inf" => return Some(DynType::Number(f64::INFINITY)),
+        "-inf" => return Some(DynType::Number(f64::NEG_INFINITY)),
+        "nan" => return Some(DynType::Number(f64::NAN)),
+        _ => {}
+    }
+    let (negative, unsigned) = match text.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let with_sign = |number: BigInt| if negative { -&number } else { number };
+
+    let radix = match unsigned.get(..2) {
+        Some("0x" | "0X") => Some(16),
+        Some("0o" | "0O") => Some(8),
+        Some("0b" | "0B") => Some(2),
+        _ => None,
+    };
+    if let Some(radix) = radix {
+        return Some(integer(with_sign(parse_integer(&unsigned[2..], radix)?)));
+    }
+    if let Some((numerator, denominator)) = unsigned.split_once('/') {
+        let numerator = parse_integer(numerator, 10)?;
+        let denominator = parse_integer(denominator, 10)?;
+        if denominator.is_zero() {
+            return None;
+        }
+        return Some(rational(Rational::new(with_sign(numerator), denominator)));
+    }
+    if unsigned.contains(['.', 'e', 'E']) {
+        let number = parse_float(unsigned)?;
+        return Some(DynType::Number(if negative { -number } else { number }));
+    }
+    Some(integer(with_sign(parse_integer(unsigned, 10)?)))
+}
+
+// Floats are printed so the lexer reads them back as floats: `1.0`, `1e300`, `inf`, `nan`
+pub fn float_to_string(number: f64) -> String {
+    if number.is_nan() {
+        String::from("nan")
+    } else if number.is_infinite() {
+        String::from(if number > 0.0 { "inf" } else { "-inf" })
+    } else if number != 0.0 && (number.abs() >= 1e16 || number.abs() < 1e-5) {
+        format!("{:e}", number)
+    } else {
+        let string = number.to_string();
+        if string.contains('.') {
+            string
+        } else {
+            string + ".0"
+        }
+    }
+}
+
+fn division_by_zero() -> Exception {
+    Exception::from("Division by zero")
+}
+
+// i64 operations which overflow are repeated with big integers
+pub fn add(x: &DynType, y: &DynType) -> Result<DynType, Exception> {
+    match operands(x, y)? {
+        Operands::Integers(x, y) => Ok(x.checked_add(y).map_or_else(
+            || integer(&BigInt::from(x) + &BigInt::from(y)),
+            DynType::Integer,
+        )),
+        Operands::BigIntegers(x, y) => Ok(integer(&x + &y)),
+        Operands::Rationals(x, y) => Ok(rational(&x + &y)),
+        Operands::Floats(x, y) => Ok(DynType::Number(x + y)),
+    }
+}
+
+pub fn sub(x: &DynType, y: &DynType) -> Result<DynType, Exception> {
+    match operands(x, y)? {
+        Operands::Integers(x, y) => Ok(x.checked_sub(y).map_or_else(
+            || integer(&BigInt::from(x) - &BigInt::from(y)),
+            DynType::Integer,
+        )),
+        Operands::BigIntegers(x, y) => Ok(integer(&x - &y)),
+        Operands::Rationals(x, y) => Ok(rational(&x - &y)),
+        Operands::Floats(x, y) => Ok(DynType::Number(x - y)),
+    }
+}
+
+pub fn mul(x: &DynType, y: &DynType) -> Result<DynType, Exception> {
+    match operands(x, y)? {
+        Operands::Integers(x, y) => Ok(x.checked_mul(y).map_or_else(
+            || integer(&BigInt::from(x) * &BigInt::from(y)),
+            DynType::Integer,
+        )),
+        Operands::BigIntegers(x, y) => Ok(integer(&x * &y)),
+        Operands::Rationals(x, y) => Ok(rational(&x * &y)),
+        Operands::Floats(x, y) => Ok(DynType::Number(x * y)),
+    }
+}
+
+// `/` of exact numbers is exact, so `(/ 1 3)` is 1/3, `div` is for the integer division
+pub fn div(x: &DynType, y: &DynType) -> Result<DynType, Exception> {
+    match operands(x, y)? {
+        Operands::Floats(x, y) => Ok(DynType::Number(x / y)),
+        _ => {
+            let (x, y) = (to_rational(x), to_rational(y));
+            if y.is_zero() {
+                Err(division_by_zero())
+            } else {
+                Ok(rational(&x / &y))
+            }
+        }
+    }
+}
+
+// the quotient is rounded down, so `(div -7 2)` is -4
+pub fn floor_div(x: &DynType, y: &DynType) -> Result<DynType, Exception> {
+    match operands(x, y)? {
+        Operands::Integers(_, 0) => Err(division_by_zero()),
+        // the only overflow is i64::MIN divided by -1
+        Operands::Integers(x, -1) => negate(&DynType::Integer(x)),
+        Operands::Integers(x, y) => {
+            let quotient = x / y;
+            if x % y != 0 && (x < 0) != (y < 0) {
+                Ok(DynType::Integer(quotient - 1))
+            } else {
+                Ok(DynType::Integer(quotient))
+            }
+        }
+        Operands::BigIntegers(_, y) if y.is_zero() => Err(division_by_zero()),
+        Operands::BigIntegers(x, y) => Ok(integer(x.div_floor(&y))),
+        Operands::Rationals(_, y) if y.is_zero() => Err(division_by_zero()),
+        Operands::Rationals(x, y) => Ok(integer((&x / &y).floor())),
+        Operands::Floats(x, y) => Ok(DynType::Number((x / y).floor())),
+    }
+}
+
+// the remainder has the sign of the divisor, so `(% -7 2)` is 1
+pub fn modulo(x: &DynType, y: &DynType) -> Result<DynType, Exception> {
+    match operands(x, y)? {
+        Operands::Integers(_, 0) => Err(division_by_zero()),
+        // the remainder of i64::MIN and -1 is 0, but the checked one overflows
+        Operands::Integers(_, -1) => Ok(DynType::Integer(0)),
+        Operands::Integers(x, y) => {
+            let remainder = x % y;
+            if remainder != 0 && (remainder < 0) != (y < 0) {
+                Ok(DynType::Integer(remainder + y))
+            } else {
+                Ok(DynType::Integer(remainder))
+            }
+        }
+        Operands::BigIntegers(_, y) if y.is_zero() => Err(division_by_zero()),
+        Operands::BigIntegers(x, y) => Ok(integer(x.mod_floor(&y))),
+        Operands::Rationals(_, y) if y.is_zero() => Err(division_by_zero()),
+        Operands::Rationals(x, y) => {
+            let quotient = Rational::from_integer((&x / &y).floor());
+            Ok(rational(&x - &(&y * &quotient)))
+        }
+        Operands::Floats(x, y) => {
+            let remainder = x % y;
+            if remainder != 0.0 && (remainder < 0.0) != (y < 0.0) {
+                Ok(DynType::Number(remainder + y))
+            } else {
+                Ok(DynType::Number(remainder))
+            }
+        }
+    }
+}
+
+pub fn negate(x: &DynType) -> Result<DynType, Exception> {
+    sub(&DynType::Integer(0), x)
+}
+
+pub enum Rounding {
+    Floor,
+    Ceil,
+    Round,
+}
+
+// Converts the number to an integer by the rounding, integers stay as they are
+pub fn round_with(x: &DynType, rounding: Rounding) -> Result<DynType, Exception> {
+    let number = match x {
+        DynType::Integer(number) => return Ok(DynType::Integer(*number)),
+        DynType::BigInteger(number) => return Ok(DynType::BigInteger(number.clone())),
+        DynType::Rational(number) => number.clone(),
+        DynType::Number(number) if number.is_finite() => Rational::from_f64(*number),
+        DynType::Number(number) => {
+            return Err(Exception::from(format!(
+                "{} cannot be converted to Integer",
+                number
+            )))
+        }
+        other => return Err(not_a_number(other)),
+    };
+    Ok(integer(match rounding {
+        Rounding::Floor => number.floor(),
+        Rounding::Ceil => number.ceil(),
+        Rounding::Round => number.round(),
+    }))
+}
+
+// compares the numbers by their values, so 1, 1.0 and 2/2 are equal
+pub fn compare(x: &DynType, y: &DynType) -> Option<Ordering> {
+    match (level(x).ok()?, level(y).ok()?) {
+        (0, 0) => Some(x.to_integer().ok()?.cmp(&y.to_integer().ok()?)),
+        (3, 3) => x.to_number().ok()?.partial_cmp(&y.to_number().ok()?),
+        (3, _) => compare_exact_float(y, x.to_number().ok()?).map(Ordering::reverse),
+        (_, 3) => compare_exact_float(x, y.to_number().ok()?),
+        (0 | 1, 0 | 1) => Some(to_big_integer(x).cmp(&to_big_integer(y))),
+        _ => Some(to_rational(x).cmp(&to_rational(y))),
+    }
+}
+
+// floats are not exact, so the finite ones are converted to the exact numbers
+fn compare_exact_float(x: &DynType, y: f64) -> Option<Ordering> {
+    if y.is_nan() {
+        None
+    } else if y == f64::INFINITY {
+        Some(Ordering::Less)
+    } else if y == f64::NEG_INFINITY {
+        Some(Ordering::Greater)
+    } else {
+        Some(to_rational(x).cmp(&Rational::from_f64(y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floats_are_read_back() {
+        for number in [1.0, -2.5, 1e300, 2.5e-7, 0.1, 123456.789] {
+            let text = float_to_string(number);
+            assert_eq!(parse(&text), Some(DynType::Number(number)), "{}", text);
+        }
+    }
+
+    #[test]
+    fn literals_of_one_number_are_equal() {
+        let expected = parse("255");
+        for text in ["0xff", "0XFF", "0o377", "0b1111_1111", "2_55", "510/2"] {
+            assert_eq!(parse(text), expected, "{}", text);
+        }
+        assert_eq!(parse("1e3"), parse("1_000.0"));
+    }
+
+    #[test]
+    fn integers_overflow_into_big_integers() {
+        let max = DynType::Integer(i64::MAX);
+        let min = DynType::Integer(i64::MIN);
+        let one = DynType::Integer(1);
+        let big = |text: &str| parse(text).unwrap();
+        assert_eq!(add(&max, &one).unwrap(), big("9223372036854775808"));
+        assert_eq!(sub(&min, &one).unwrap(), big("-9223372036854775809"));
+        assert_eq!(
+            mul(&max, &max).unwrap(),
+            big("85070591730234615847396907784232501249")
+        );
+        assert_eq!(negate(&min).unwrap(), big("9223372036854775808"));
+        assert_eq!(
+            floor_div(&min, &DynType::Integer(-1)).unwrap(),
+            big("9223372036854775808")
+        );
+        assert_eq!(
+            modulo(&min, &DynType::Integer(-1)).unwrap(),
+            DynType::Integer(0)
+        );
+
+        // results which fit again become integers
+        let sum = add(&max, &one).unwrap();
+        assert_eq!(sub(&sum, &one).unwrap(), max);
+        assert_eq!(
+            div(&big("18446744073709551616"), &big("4294967296")).unwrap(),
+            big("4294967296")
+        );
+        assert!(matches!(add(&max, &one).unwrap(), DynType::BigInteger(_)));
+        assert!(matches!(sub(&sum, &one).unwrap(), DynType::Integer(_)));
+    }
+
+    #[test]
+    fn integers_divide_into_rationals() {
+        let (seven, two) = (DynType::Integer(7), DynType::Integer(2));
+        assert_eq!(div(&seven, &two).unwrap(), parse("7/2").unwrap());
+        assert_eq!(
+            floor_div(&DynType::Integer(-7), &two).unwrap(),
+            DynType::Integer(-4)
+        );
+        assert_eq!(
+            modulo(&DynType::Integer(-7), &two).unwrap(),
+            DynType::Integer(1)
+        );
+        assert!(div(&seven, &DynType::Integer(0)).is_err());
+    }
+}
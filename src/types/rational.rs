@@ -0,0 +1,240 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    ops::{Add, Div, Mul, Sub},
+};
+
+use super::bigint::BigInt;
+
+// Exact fraction. It is always reduced and the denominator is positive,
+// so equal fractions have the same parts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rational {
+    numerator: BigInt,
+    denominator: BigInt,
+}
+
+impl Rational {
+    // the denominator must not be zero
+    pub fn new(numerator: BigInt, denominator: BigInt) -> Rational {
+        let gcd = numerator.gcd(&denominator);
+        let (mut numerator, _) = numerator.div_rem(&gcd);
+        let (mut denominator, _) = denominator.div_rem(&gcd);
+        if denominator.is_negative() {
+            numerator = -&numerator;
+            denominator = -&denominator;
+        }
+        Rational {
+            numerator,
+            denominator,
+        }
+    }
+
+    pub fn from_integer(integer: BigInt) -> Rational {
+        Rational {
+            numerator: integer,
+            denominator: BigInt::one(),
+        }
+    }
+
+    // every finite float is a fraction with a power of two in the denominator
+    pub fn from_f64(number: f64) -> Rational {
+        let bits = number.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i64;
+        let mantissa = if exponent == 0 {
+            (bits & 0xf_ffff_ffff_ffff) << 1
+        } else {
+            (bits & 0xf_ffff_ffff_ffff) | (1 << 52)
+        };
+        let exponent = exponent - 1075;
+        let mantissa = BigInt::from(mantissa as i64);
+        let mantissa = if number < 0.0 { -&mantissa } else { mantissa };
+        if exponent >= 0 {
+            Rational::from_integer(mantissa.shift_left(exponent as u32))
+        } else {
+            Rational::new(mantissa, BigInt::one().shift_left((-exponent) as u32))
+        }
+    }
+
+    pub fn numerator(&self) -> &BigInt {
+        &self.numerator
+    }
+
+    pub fn denominator(&self) -> &BigInt {
+        &self.denominator
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denominator == BigInt::one()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numerator.is_zero()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let numerator = self.numerator.to_f64();
+        let denominator = self.denominator.to_f64();
+        if numerator.is_finite() && denominator.is_finite() {
+            return numerator / denominator;
+        }
+        // the parts are too big for floats, so the quotient is taken with 64 significant bits
+        let shift =
+            (self.denominator.bits() as i64 - self.numerator.bits() as i64 + 64).clamp(-2000, 2000);
+        let (quotient, _) = if shift >= 0 {
+            self.numerator
+                .shift_left(shift as u32)
+                .div_rem(&self.denominator)
+        } else {
+            self.numerator
+                .div_rem(&self.denominator.shift_left(-shift as u32))
+        };
+        quotient.to_f64() * 2f64.powi(-shift as i32)
+    }
+
+    pub fn floor(&self) -> BigInt {
+        self.numerator.div_floor(&self.denominator)
+    }
+
+    pub fn ceil(&self) -> BigInt {
+        -&(-&self.numerator).div_floor(&self.denominator)
+    }
+
+    // halves are rounded away from zero, like `f64::round` does
+    pub fn round(&self) -> BigInt {
+        let doubled = &self.numerator.abs().shift_left(1) + &self.denominator;
+        let rounded = doubled.div_floor(&self.denominator.shift_left(1));
+        if self.numerator.is_negative() {
+            -&rounded
+        } else {
+            rounded
+        }
+    }
+}
+
+impl Add for &Rational {
+    type Output = Rational;
+
+    fn add(self, other: &Rational) -> Rational {
+        Rational::new(
+            &(&self.numerator * &other.denominator) + &(&other.numerator * &self.denominator),
+            &self.denominator * &other.denominator,
+        )
+    }
+}
+
+impl Sub for &Rational {
+    type Output = Rational;
+
+    fn sub(self, other: &Rational) -> Rational {
+        Rational::new(
+            &(&self.numerator * &other.denominator) - &(&other.numerator * &self.denominator),
+            &self.denominator * &other.denominator,
+        )
+    }
+}
+
+impl Mul for &Rational {
+    type Output = Rational;
+
+    fn mul(self, other: &Rational) -> Rational {
+        Rational::new(
+            &self.numerator * &other.numerator,
+            &self.denominator * &other.denominator,
+        )
+    }
+}
+
+// the divisor must not be zero
+impl Div for &Rational {
+    type Output = Rational;
+
+    fn div(self, other: &Rational) -> Rational {
+        Rational::new(
+            &self.numerator * &other.denominator,
+            &self.denominator * &other.numerator,
+        )
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.numerator * &other.denominator).cmp(&(&other.numerator * &self.denominator))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio(numerator: i64, denominator: i64) -> Rational {
+        Rational::new(BigInt::from(numerator), BigInt::from(denominator))
+    }
+
+    #[test]
+    fn fractions_are_reduced() {
+        assert_eq!(ratio(6, 8).to_string(), "3/4");
+        assert_eq!(ratio(6, -8).to_string(), "-3/4");
+        assert_eq!(ratio(-6, -8).to_string(), "3/4");
+        assert_eq!(ratio(0, -5), ratio(0, 1));
+        assert!(ratio(10, 5).is_integer());
+        assert_eq!(ratio(10, 5).numerator(), &BigInt::from(2));
+
+        let big = BigInt::from(i64::MAX);
+        let reduced = Rational::new(&big * &BigInt::from(3), &big * &BigInt::from(-6));
+        assert_eq!(reduced, ratio(-1, 2));
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(&ratio(1, 2) + &ratio(1, 3), ratio(5, 6));
+        assert_eq!(&ratio(1, 2) - &ratio(5, 6), ratio(-1, 3));
+        assert_eq!(&ratio(-2, 3) * &ratio(9, 4), ratio(-3, 2));
+        assert_eq!(&ratio(1, 2) / &ratio(-1, 4), ratio(-2, 1));
+        assert!(&ratio(1, 3) + &ratio(2, 3) == ratio(1, 1));
+        assert!(ratio(-1, 2) < ratio(-1, 3));
+        assert!(ratio(7, 3) > ratio(2, 1));
+    }
+
+    #[test]
+    fn rounding_of_negative_fractions() {
+        let cases = [
+            (ratio(7, 2), (3, 4, 4)),
+            (ratio(-7, 2), (-4, -3, -4)),
+            (ratio(-5, 3), (-2, -1, -2)),
+            (ratio(-4, 3), (-2, -1, -1)),
+            (ratio(-6, 3), (-2, -2, -2)),
+        ];
+        for (number, (floor, ceil, round)) in cases {
+            assert_eq!(number.floor(), BigInt::from(floor), "floor {}", number);
+            assert_eq!(number.ceil(), BigInt::from(ceil), "ceil {}", number);
+            assert_eq!(number.round(), BigInt::from(round), "round {}", number);
+        }
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(Rational::from_f64(0.75), ratio(3, 4));
+        assert_eq!(Rational::from_f64(-2.5), ratio(-5, 2));
+        assert_eq!(Rational::from_f64(1e20).to_f64(), 1e20);
+        assert_eq!(ratio(-1, 3).to_f64(), -1.0 / 3.0);
+
+        let huge = BigInt::from(10).shift_left(2000);
+        let third = Rational::new(huge.clone(), &huge * &BigInt::from(3));
+        assert_eq!(third.to_f64(), 1.0 / 3.0);
+        let big = Rational::new(&huge + &BigInt::one(), huge.shift_left(1));
+        assert_eq!(big.to_f64(), 0.5);
+    }
+}
@@ -2,24 +2,24 @@ use std::{fmt::Display, rc::Rc};
 
 use crate::types::DynType;
 
-use super::{exception::Exception, list::List, value::Value};
+use super::{exception::Exception, list::List, show, symbol::Symbol, value::Value};
 
 #[derive(Debug)]
 pub struct StructType {
-    pub name: String,
-    pub fields: Vec<String>, // field name and its index
+    pub name: Symbol,
+    pub fields: Vec<Symbol>, // field name and its index
 }
 
 impl Display for StructType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut string = String::from("(record ");
 
-        string.push_str(self.name.as_str());
+        string.push_str(&self.name.name());
         string.push_str(" (");
 
         let mut fields = vec![];
         for field_name in &self.fields {
-            fields.push(field_name.clone());
+            fields.push(field_name.name());
         }
         string.push_str(fields.join(" ").as_str());
         string.push_str("))");
@@ -45,12 +45,12 @@ impl Struct {
         Ok(Self { struct_type, data })
     }
 
-    pub fn get_field(self, required_field: String) -> Result<Value, Exception> {
+    pub fn get_field(self, required_field: Symbol) -> Result<Value, Exception> {
         let mut fields = List::new(self.data);
 
-        for name in &(&*self.struct_type).fields {
+        for name in &self.struct_type.fields {
             let item = fields.next().to_middle()?;
-            if name.eq(&required_field) {
+            if *name == required_field {
                 return Ok(item);
             }
         }
@@ -71,13 +71,15 @@ impl Display for Struct {
         let mut list = List::new(self.data.clone());
 
         let mut string = String::from('(');
-        string.push_str(self.struct_type.name.as_str());
+        string.push_str(&self.struct_type.name.name());
 
         string.push_str(" (");
         for field_name in &self.struct_type.fields {
             let value = list.next().to_middle();
             if let Ok(v) = value {
-                string.push_str(format!("({} {}) ", field_name, v.content).as_str());
+                string.push_str(
+                    format!("({} {}) ", field_name, show(&v.content, f.alternate())).as_str(),
+                );
             } else {
                 string.push_str(format!("({} ??) ", field_name).as_str())
             }
@@ -0,0 +1,68 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{Debug, Display},
+    rc::Rc,
+};
+
+// The interner keeps the names, so the symbols are just their indexes
+#[derive(Default)]
+struct Interner {
+    names: Vec<Rc<str>>,
+    symbols: HashMap<Rc<str>, Symbol>,
+}
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner::default());
+}
+
+// Interned name. Symbols with the same name are the same id, so they are compared and hashed as numbers.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn new(name: &str) -> Symbol {
+        INTERNER.with(|interner| {
+            let mut interner = interner.borrow_mut();
+            if let Some(symbol) = interner.symbols.get(name) {
+                return *symbol;
+            }
+            let name: Rc<str> = Rc::from(name);
+            let symbol = Symbol(interner.names.len() as u32);
+            interner.names.push(name.clone());
+            interner.symbols.insert(name, symbol);
+            symbol
+        })
+    }
+
+    pub fn name(self) -> Rc<str> {
+        INTERNER.with(|interner| interner.borrow().names[self.0 as usize].clone())
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_names_are_same_symbols() {
+        let name = String::from("some-name");
+        let symbol = Symbol::new(&name);
+        assert_eq!(Symbol::new("some-name"), symbol);
+        assert_ne!(Symbol::new("other-name"), symbol);
+        assert_eq!(&*symbol.name(), "some-name");
+        assert_eq!(symbol.to_string(), "some-name");
+    }
+}
@@ -5,11 +5,11 @@ use super::DynType;
 #[derive(Clone)]
 pub struct Value {
     pub content: Rc<DynType>,
-    pub position: Option<(u32, u16)>,
+    pub position: Option<(u32, u32)>,
 }
 
 impl Value {
-    pub fn new(content: DynType, position: Option<(u32, u16)>) -> Self {
+    pub fn new(content: DynType, position: Option<(u32, u32)>) -> Self {
         Self {
             content: Rc::new(content),
             position,